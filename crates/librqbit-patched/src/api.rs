@@ -1,4 +1,7 @@
-use std::{collections::HashSet, marker::PhantomData, net::SocketAddr, str::FromStr, sync::Arc};
+use std::{
+    collections::HashSet, marker::PhantomData, net::SocketAddr, path::PathBuf, str::FromStr,
+    sync::Arc,
+};
 
 use anyhow::Context;
 use buffers::ByteBufOwned;
@@ -8,11 +11,14 @@ use librqbit_core::torrent_metainfo::{FileDetailsAttrs, TorrentMetaV1Info};
 use serde::{Deserialize, Serialize};
 use tokio::sync::mpsc::UnboundedSender;
 use tracing::warn;
+use tracker_comms::TrackerState;
 
 use crate::{
     api_error::{ApiError, ApiErrorExt},
     session::{
-        AddTorrent, AddTorrentOptions, AddTorrentResponse, ListOnlyResponse, Session, TorrentId,
+        AddTorrent, AddTorrentOptions, AddTorrentResponse, DeleteFiles, DeleteStats, ListOnlyResponse,
+        RelocateProgress,
+        Session, TorrentId,
     },
     session_stats::snapshot::SessionStatsSnapshot,
     torrent_state::{
@@ -213,8 +219,7 @@ impl Api {
                         name: mgr.name(),
                         output_folder: mgr
                             .shared()
-                            .options
-                            .output_folder
+                            .output_folder()
                             .to_string_lossy()
                             .into_owned(),
 
@@ -238,8 +243,7 @@ impl Api {
         let only_files = handle.only_files();
         let output_folder = handle
             .shared()
-            .options
-            .output_folder
+            .output_folder()
             .to_string_lossy()
             .into_owned()
             .to_string();
@@ -278,6 +282,54 @@ impl Api {
             .per_peer_stats_snapshot(filter))
     }
 
+    /// Announce results of each tracker of the torrent, and how many peers DHT and PEX found.
+    /// Available whatever state the torrent is in.
+    pub fn api_torrent_peer_sources(&self, idx: TorrentIdOrHash) -> Result<PeerSourcesResponse> {
+        let handle = self.mgr_handle(idx)?;
+        let sources = handle.shared().peer_sources();
+        Ok(PeerSourcesResponse {
+            trackers: sources
+                .trackers()
+                .snapshot()
+                .into_iter()
+                .map(|(url, state)| TrackerStatus {
+                    url: url.to_string(),
+                    state,
+                })
+                .collect(),
+            dht_peers: sources.dht_peers(),
+            pex_peers: sources.pex_peers(),
+        })
+    }
+
+    /// Announce to `tracker`, or to all trackers of the torrent, now rather than when their
+    /// interval runs out. Peer connections are left alone. A tracker's min interval is still
+    /// respected unless `force` is set.
+    pub fn api_torrent_action_reannounce(
+        &self,
+        idx: TorrentIdOrHash,
+        tracker: Option<url::Url>,
+        force: bool,
+    ) -> Result<EmptyJsonResponse> {
+        let handle = self.mgr_handle(idx)?;
+        handle
+            .live()
+            .context("torrent is not live")
+            .with_error_status_code(StatusCode::BAD_REQUEST)?;
+        let woken = handle
+            .shared()
+            .peer_sources()
+            .trackers()
+            .reannounce(tracker.as_ref(), force);
+        if woken == 0 && tracker.is_some() {
+            return Err(ApiError::new_from_text(
+                StatusCode::NOT_FOUND,
+                "torrent does not announce to this tracker",
+            ));
+        }
+        Ok(Default::default())
+    }
+
     pub async fn api_torrent_action_pause(
         &self,
         idx: TorrentIdOrHash,
@@ -326,6 +378,36 @@ impl Api {
         Ok(Default::default())
     }
 
+    /// Remove a torrent, deleting, moving or keeping its files, and report what was removed.
+    pub async fn api_torrent_action_delete_with(
+        &self,
+        idx: TorrentIdOrHash,
+        files: DeleteFiles,
+    ) -> Result<DeleteStats> {
+        let stats = self
+            .session
+            .delete_with(idx, files)
+            .await
+            .context("error deleting torrent")?;
+        Ok(stats)
+    }
+
+    /// Move a torrent's files to `output_folder`, keeping its piece state.
+    pub async fn api_torrent_action_relocate(
+        &self,
+        idx: TorrentIdOrHash,
+        output_folder: PathBuf,
+        progress: Arc<RelocateProgress>,
+    ) -> Result<EmptyJsonResponse> {
+        let handle = self.mgr_handle(idx)?;
+        self.session
+            .relocate(&handle, output_folder, progress)
+            .await
+            .context("error moving torrent")
+            .with_error_status_code(StatusCode::BAD_REQUEST)?;
+        Ok(Default::default())
+    }
+
     pub async fn api_torrent_action_update_only_files(
         &self,
         idx: TorrentIdOrHash,
@@ -339,6 +421,20 @@ impl Api {
         Ok(Default::default())
     }
 
+    /// Replace the torrent's own trackers, by BEP 12 tier. Takes effect without restarting it.
+    pub async fn api_torrent_action_update_trackers(
+        &self,
+        idx: TorrentIdOrHash,
+        tiers: Vec<Vec<url::Url>>,
+    ) -> Result<EmptyJsonResponse> {
+        let handle = self.mgr_handle(idx)?;
+        self.session
+            .set_torrent_trackers(&handle, tiers)
+            .await
+            .context("error updating trackers")?;
+        Ok(Default::default())
+    }
+
     pub fn api_set_rust_log(&self, new_value: String) -> Result<EmptyJsonResponse> {
         let tx = self
             .rust_log_reload_tx
@@ -386,8 +482,7 @@ impl Api {
                     handle.only_files().as_deref(),
                     handle
                         .shared()
-                        .options
-                        .output_folder
+                        .output_folder()
                         .to_string_lossy()
                         .into_owned(),
                 )
@@ -398,8 +493,7 @@ impl Api {
                     seen_peers: None,
                     output_folder: handle
                         .shared()
-                        .options
-                        .output_folder
+                        .output_folder()
                         .to_string_lossy()
                         .into_owned(),
                 }
@@ -434,8 +528,7 @@ impl Api {
                     handle.only_files().as_deref(),
                     handle
                         .shared()
-                        .options
-                        .output_folder
+                        .output_folder()
                         .to_string_lossy()
                         .into_owned(),
                 )
@@ -446,8 +539,7 @@ impl Api {
                     seen_peers: None,
                     output_folder: handle
                         .shared()
-                        .options
-                        .output_folder
+                        .output_folder()
                         .to_string_lossy()
                         .into_owned(),
                 }
@@ -522,6 +614,20 @@ pub struct TorrentDetailsResponse {
     pub stats: Option<TorrentStats>,
 }
 
+#[derive(Serialize)]
+pub struct TrackerStatus {
+    pub url: String,
+    #[serde(flatten)]
+    pub state: TrackerState,
+}
+
+#[derive(Serialize)]
+pub struct PeerSourcesResponse {
+    pub trackers: Vec<TrackerStatus>,
+    pub dht_peers: u64,
+    pub pex_peers: u64,
+}
+
 #[derive(Serialize, Deserialize)]
 pub struct ApiAddTorrentResponse {
     pub id: Option<usize>,
@@ -0,0 +1,122 @@
+//! Pinning the session's sockets to one network interface or local address.
+//!
+//! With [`SessionOptions::bind`](crate::SessionOptions::bind) set, the peer listener, outbound
+//! peer and tracker connections, the UDP tracker socket and the DHT all use it. Nothing falls
+//! back to the default route: once the interface or address is gone, binding fails and so do
+//! the connections.
+
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+
+use anyhow::Context;
+use tokio::net::{TcpListener, TcpSocket, UdpSocket};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BindTarget {
+    /// Bind to the device (`SO_BINDTODEVICE`). Linux only.
+    Interface(String),
+    /// Use this local address. Peers of the other address family are unreachable.
+    Address(IpAddr),
+}
+
+impl BindTarget {
+    /// Interface name, for socket options that take one.
+    pub fn device(&self) -> Option<&str> {
+        match self {
+            BindTarget::Interface(name) => Some(name),
+            BindTarget::Address(_) => None,
+        }
+    }
+}
+
+impl std::fmt::Display for BindTarget {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            BindTarget::Interface(name) => f.write_str(name),
+            BindTarget::Address(ip) => write!(f, "{ip}"),
+        }
+    }
+}
+
+/// Local address for a listening socket on `port`.
+pub(crate) fn listen_addr(bind: Option<&BindTarget>, port: u16) -> SocketAddr {
+    let ip = match bind {
+        Some(BindTarget::Address(ip)) => *ip,
+        _ => Ipv4Addr::UNSPECIFIED.into(),
+    };
+    SocketAddr::new(ip, port)
+}
+
+#[cfg(any(target_os = "linux", target_os = "android"))]
+fn bind_tcp_device(socket: &TcpSocket, name: &str) -> anyhow::Result<()> {
+    socket
+        .bind_device(Some(name.as_bytes()))
+        .with_context(|| format!("error binding to interface {name}"))
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "android")))]
+fn bind_tcp_device(_socket: &TcpSocket, name: &str) -> anyhow::Result<()> {
+    anyhow::bail!("cannot bind to interface {name}: only supported on Linux, bind to its address instead")
+}
+
+#[cfg(any(target_os = "linux", target_os = "android"))]
+fn bind_udp_device(socket: &UdpSocket, name: &str) -> anyhow::Result<()> {
+    socket
+        .bind_device(Some(name.as_bytes()))
+        .with_context(|| format!("error binding to interface {name}"))
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "android")))]
+fn bind_udp_device(_socket: &UdpSocket, name: &str) -> anyhow::Result<()> {
+    anyhow::bail!("cannot bind to interface {name}: only supported on Linux, bind to its address instead")
+}
+
+fn new_tcp_socket(addr: SocketAddr) -> std::io::Result<TcpSocket> {
+    if addr.is_ipv6() {
+        TcpSocket::new_v6()
+    } else {
+        TcpSocket::new_v4()
+    }
+}
+
+pub(crate) fn tcp_listener(bind: Option<&BindTarget>, port: u16) -> anyhow::Result<TcpListener> {
+    let addr = listen_addr(bind, port);
+    let socket = new_tcp_socket(addr)?;
+    // As `TcpListener::bind` does, so a restart can reuse the port right away.
+    #[cfg(not(windows))]
+    socket.set_reuseaddr(true)?;
+    if let Some(name) = bind.and_then(BindTarget::device) {
+        bind_tcp_device(&socket, name)?;
+    }
+    socket
+        .bind(addr)
+        .with_context(|| format!("error binding to {addr}"))?;
+    Ok(socket.listen(1024)?)
+}
+
+/// An unconnected socket for reaching `remote` from the bound interface or address.
+pub(crate) fn outbound_tcp_socket(bind: &BindTarget, remote: SocketAddr) -> anyhow::Result<TcpSocket> {
+    let socket = new_tcp_socket(remote)?;
+    match bind {
+        BindTarget::Interface(name) => bind_tcp_device(&socket, name)?,
+        BindTarget::Address(ip) => {
+            if ip.is_ipv6() != remote.is_ipv6() {
+                anyhow::bail!("{remote} is unreachable from bind address {ip}");
+            }
+            socket
+                .bind(SocketAddr::new(*ip, 0))
+                .with_context(|| format!("error binding to {ip}"))?;
+        }
+    }
+    Ok(socket)
+}
+
+pub(crate) async fn udp_socket(bind: Option<&BindTarget>, port: u16) -> anyhow::Result<UdpSocket> {
+    let addr = listen_addr(bind, port);
+    let socket = UdpSocket::bind(addr)
+        .await
+        .with_context(|| format!("error binding UDP to {addr}"))?;
+    if let Some(name) = bind.and_then(BindTarget::device) {
+        bind_udp_device(&socket, name)?;
+    }
+    Ok(socket)
+}
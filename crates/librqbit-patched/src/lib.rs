@@ -40,6 +40,7 @@ macro_rules! aframe {
 
 pub mod api;
 mod api_error;
+mod bind;
 mod bitv;
 mod bitv_factory;
 mod blocklist;
@@ -76,18 +77,21 @@ pub mod watch;
 
 pub use api::Api;
 pub use api_error::ApiError;
+pub use bind::BindTarget;
 pub use create_torrent_file::{create_torrent, CreateTorrentOptions};
 pub use dht;
 pub use peer_connection::PeerConnectionOptions;
 pub use session::{
-    AddTorrent, AddTorrentOptions, AddTorrentResponse, ListOnlyResponse, Session, SessionOptions,
-    SessionPersistenceConfig, SUPPORTED_SCHEMES,
+    AddTorrent, AddTorrentOptions, AddTorrentResponse, DeleteFiles, DeleteStats, ListOnlyResponse,
+    ListenInfo, RelocateProgress, Session, SessionOptions, SessionPersistenceConfig, TorrentIsolation,
+    SUPPORTED_SCHEMES,
 };
 pub use spawn_utils::spawn as librqbit_spawn;
 pub use torrent_state::{
-    ManagedTorrent, ManagedTorrentShared, ManagedTorrentState, TorrentMetadata, TorrentStats,
-    TorrentStatsState,
+    ManagedTorrent, ManagedTorrentShared, ManagedTorrentState, PeerSources, TorrentMetadata,
+    TorrentStats, TorrentStatsState,
 };
+pub use tracker_comms::{redact_tracker_url, TrackerState, TrackerStates};
 pub use type_aliases::FileInfos;
 
 pub use buffers::*;
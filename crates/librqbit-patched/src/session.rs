@@ -2,14 +2,18 @@ use std::{
     borrow::Cow,
     collections::{HashMap, HashSet},
     io::Read,
-    net::SocketAddr,
+    net::{IpAddr, SocketAddr},
     path::{Component, Path, PathBuf},
-    sync::{atomic::AtomicUsize, Arc},
+    sync::{
+        atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering},
+        Arc,
+    },
     time::Duration,
 };
 
 use crate::{
     api::TorrentIdOrHash,
+    bind::{self, BindTarget},
     bitv_factory::{BitVFactory, NonPersistentBitVFactory},
     blocklist,
     dht_utils::{read_metainfo_from_peer_receiver, ReadMetainfoResult},
@@ -23,10 +27,10 @@ use crate::{
     storage::{
         filesystem::FilesystemStorageFactory, BoxStorageFactory, StorageFactoryExt, TorrentStorage,
     },
-    stream_connect::{SocksProxyConfig, StreamConnector},
+    stream_connect::{OutboundGate, SocksProxyConfig, StreamConnector},
     torrent_state::{
-        initializing::TorrentStateInitializing, ManagedTorrentHandle, ManagedTorrentLocked,
-        ManagedTorrentOptions, ManagedTorrentState, TorrentMetadata, TorrentStateLive,
+        initializing::TorrentStateInitializing, paused::TorrentStatePaused, ManagedTorrentHandle, ManagedTorrentLocked,
+        ManagedTorrentOptions, ManagedTorrentState, PeerSources, TorrentMetadata, TorrentStateLive,
     },
     type_aliases::{DiskWorkQueueSender, PeerStream},
     FileInfos, ManagedTorrent, ManagedTorrentShared,
@@ -62,10 +66,17 @@ use tokio::{
 };
 use tokio_util::sync::{CancellationToken, DropGuard};
 use tracing::{debug, error, error_span, info, trace, warn, Instrument, Span};
-use tracker_comms::{TrackerComms, UdpTrackerClient};
+use librqbit_upnp::{UpnpForwardStatus, UpnpPortForwarder};
+use tracker_comms::{
+    redact_tracker_url, AnnounceTargets, TrackerComms, TrackerStates, UdpTrackerClient,
+};
 
 pub const SUPPORTED_SCHEMES: [&str; 3] = ["http:", "https:", "magnet:"];
 
+/// How fresh each tracker's swarm counts are kept by scraping.
+const SCRAPE_INTERVAL: Duration = Duration::from_secs(30 * 60);
+const SCRAPE_CONCURRENCY: usize = 8;
+
 pub type TorrentId = usize;
 
 struct ParsedTorrentFile {
@@ -107,11 +118,24 @@ pub struct Session {
 
     // Network
     peer_id: Id20,
-    tcp_listen_port: Option<u16>,
+    listener: RwLock<ListenerState>,
+    enable_upnp_port_forwarding: AtomicBool,
     dht: Option<Dht>,
+    // Runtime switches, see [`Session::set_dht_peer_discovery`] and [`Session::set_ipv6_peers`].
+    dht_peer_discovery: Arc<AtomicBool>,
+    ipv6_peers: AtomicBool,
+    pex: AtomicBool,
+    uploads_blocked: AtomicBool,
+    scraping_blocked: AtomicBool,
     pub(crate) connector: Arc<StreamConnector>,
+    outbound_gate: OutboundGate,
     reqwest_client: reqwest::Client,
     udp_tracker_client: UdpTrackerClient,
+    bind: Option<BindTarget>,
+    // For torrents with `TorrentIsolation::proxy_peers`; refuses connections without a proxy.
+    proxy_connector: Arc<StreamConnector>,
+    // For torrents with `TorrentIsolation::proxy_trackers`.
+    proxy_reqwest_client: Option<reqwest::Client>,
 
     // Lifecycle management
     cancellation_token: CancellationToken,
@@ -279,6 +303,41 @@ pub struct AddTorrentOptions {
 
     // Custom trackers
     pub trackers: Option<Vec<String>>,
+
+    /// Announce to these trackers, by tier, instead of the ones in the torrent or magnet.
+    /// [`AddTorrentOptions::trackers`] are still added. Used to restore trackers changed with
+    /// [`Session::set_torrent_trackers`].
+    #[serde(default)]
+    pub tracker_tiers: Option<Vec<Vec<String>>>,
+
+    /// How this torrent may reach the network. Can be changed later with
+    /// [`Session::set_torrent_isolation`].
+    #[serde(default)]
+    pub isolation: TorrentIsolation,
+}
+
+/// Per-torrent restrictions on peer discovery and connections, on top of the session-wide ones.
+/// The default restricts nothing.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TorrentIsolation {
+    /// Behave as a private torrent (BEP 27) whatever its metainfo says: no DHT, no PEX and no
+    /// session-wide trackers.
+    #[serde(default)]
+    pub private: bool,
+    /// No DHT lookups or announces.
+    #[serde(default)]
+    pub disable_dht: bool,
+    /// Connect to peers only through [`SessionOptions::torrent_proxy_url`]. Without one, no
+    /// outbound peer connections are made.
+    #[serde(default)]
+    pub proxy_peers: bool,
+    /// Announce only through [`SessionOptions::torrent_proxy_url`]. UDP trackers cannot go
+    /// through a SOCKS5 proxy and are skipped, as are all trackers without a proxy.
+    #[serde(default)]
+    pub proxy_trackers: bool,
+    /// Refuse incoming peer connections and announce no listen port.
+    #[serde(default)]
+    pub no_incoming: bool,
 }
 
 pub struct ListOnlyResponse {
@@ -413,6 +472,14 @@ pub struct SessionOptions {
     // socks5://[username:password@]host:port
     pub socks_proxy_url: Option<String>,
 
+    /// Proxy for torrents whose [`TorrentIsolation`] asks for one, in the same format as
+    /// `socks_proxy_url`. Other torrents do not use it.
+    pub torrent_proxy_url: Option<String>,
+
+    /// Bind the peer listener, outbound peer and tracker connections and the DHT to this
+    /// interface or address. Connections fail rather than use another route while it is gone.
+    pub bind: Option<BindTarget>,
+
     pub cancellation_token: Option<CancellationToken>,
 
     // how many concurrent torrent initializations can happen
@@ -432,18 +499,82 @@ pub struct SessionOptions {
     pub disable_upload: bool,
 }
 
+/// The TCP listener currently accepting peers. Replaced by [`Session::rebind_listen_port`].
+#[derive(Default)]
+struct ListenerState {
+    port: Option<u16>,
+    // Cancels the accept loop and UPnP forwarder of this listener only.
+    cancel: Option<CancellationToken>,
+    // Child of `cancel`; cancels just the UPnP forwarder.
+    upnp_cancel: Option<CancellationToken>,
+    upnp: Option<UpnpForwardStatus>,
+    // Port of a listener shut by `Session::close_listener`, to reopen on.
+    closed_port: Option<u16>,
+}
+
+/// Where the session accepts peers and whether the router forwarded the port.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub struct ListenInfo {
+    pub tcp_port: Option<u16>,
+    pub dht_port: Option<u16>,
+    pub upnp_enabled: bool,
+    /// Latest UPnP mapping result for `tcp_port`; `None` until a gateway answered.
+    pub upnp_mapped: Option<bool>,
+}
+
+/// What [`Session::delete_with`] does with a torrent's files.
+#[derive(Debug, Clone)]
+pub enum DeleteFiles {
+    Keep,
+    Delete,
+    /// Move the files into this folder, keeping their paths relative to the output folder.
+    MoveTo(PathBuf),
+}
+
+/// Files taken out of the torrent's output folder by [`Session::delete_with`].
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct DeleteStats {
+    pub files: usize,
+    pub bytes: u64,
+}
+
+/// Progress of [`Session::relocate`], updated as files are moved.
+#[derive(Debug, Default)]
+pub struct RelocateProgress {
+    pub total_bytes: AtomicU64,
+    pub moved_bytes: AtomicU64,
+}
+
 async fn create_tcp_listener(
     port_range: std::ops::Range<u16>,
+    bind: Option<&BindTarget>,
 ) -> anyhow::Result<(TcpListener, u16)> {
+    let mut last_error = None;
     for port in port_range.clone() {
-        match TcpListener::bind(("0.0.0.0", port)).await {
+        match bind::tcp_listener(bind, port) {
             Ok(l) => return Ok((l, port)),
             Err(e) => {
-                debug!("error listening on port {port}: {e:#}")
+                debug!("error listening on port {port}: {e:#}");
+                last_error = Some(e);
             }
         }
     }
-    bail!("no free TCP ports in range {port_range:?}");
+    match last_error {
+        Some(e) => Err(e.context(format!("no free TCP ports in range {port_range:?}"))),
+        None => bail!("no free TCP ports in range {port_range:?}"),
+    }
+}
+
+fn log_listening(bind: Option<&BindTarget>, port: u16) {
+    match bind {
+        Some(BindTarget::Interface(name)) => {
+            info!("Listening on {name} port {port} for incoming peer connections")
+        }
+        _ => info!(
+            "Listening on {} for incoming peer connections",
+            bind::listen_addr(bind, port)
+        ),
+    }
 }
 
 fn torrent_file_from_info_bytes(info_bytes: &[u8], trackers: &[url::Url]) -> anyhow::Result<Bytes> {
@@ -465,6 +596,85 @@ fn torrent_file_from_info_bytes(info_bytes: &[u8], trackers: &[url::Url]) -> any
     Ok(w.into())
 }
 
+// Tracker URLs by tier, skipping the ones that don't parse and tiers left empty.
+fn parse_tracker_tiers(tiers: Vec<Vec<String>>) -> Vec<Vec<url::Url>> {
+    dedup_tracker_tiers(
+        tiers
+            .into_iter()
+            .map(|tier| {
+                tier.iter()
+                    .filter_map(|t| url::Url::parse(t).ok())
+                    .collect::<Vec<_>>()
+            })
+            .collect(),
+    )
+}
+
+// Keep each tracker only in the first tier listing it, and drop tiers left empty.
+fn dedup_tracker_tiers(tiers: Vec<Vec<url::Url>>) -> Vec<Vec<url::Url>> {
+    let mut seen = HashSet::new();
+    tiers
+        .into_iter()
+        .map(|tier| {
+            tier.into_iter()
+                .filter(|t| seen.insert(t.clone()))
+                .collect::<Vec<_>>()
+        })
+        .filter(|tier| !tier.is_empty())
+        .collect()
+}
+
+// The trackers of a metainfo file by BEP 12 tier.
+fn metainfo_tracker_tiers(torrent: &TorrentMetaV1Owned) -> Vec<Vec<url::Url>> {
+    let parse = |tracker: &ByteBufOwned| match std::str::from_utf8(tracker.as_ref()) {
+        Ok(url) => url::Url::parse(url).ok(),
+        Err(_) => {
+            warn!("cannot parse tracker url as utf-8, ignoring");
+            None
+        }
+    };
+    // BEP 12: announce-list, when there is one, replaces announce.
+    let tiers = if torrent.announce_list.iter().flatten().next().is_some() {
+        torrent
+            .announce_list
+            .iter()
+            .map(|tier| tier.iter().filter_map(parse).collect())
+            .collect()
+    } else {
+        vec![torrent.announce.iter().filter_map(parse).collect()]
+    };
+    dedup_tracker_tiers(tiers)
+}
+
+/// Put trackers stored without their tiers back into the tiers of the torrent file in
+/// `torrent_bytes`. Trackers it doesn't list were added later and go last, one tier each;
+/// ones it lists that were not stored had been removed and stay out.
+pub(crate) fn retier_stored_trackers(
+    stored: &[Vec<String>],
+    torrent_bytes: Bytes,
+) -> anyhow::Result<Vec<Vec<String>>> {
+    let torrent = torrent_from_bytes(torrent_bytes)?;
+    let stored = stored
+        .iter()
+        .flatten()
+        .filter_map(|t| url::Url::parse(t).ok())
+        .collect::<Vec<_>>();
+    let mut tiers = metainfo_tracker_tiers(&torrent.info);
+    for tier in tiers.iter_mut() {
+        tier.retain(|t| stored.contains(t));
+    }
+    let added = stored
+        .iter()
+        .filter(|t| !tiers.iter().flatten().any(|listed| listed == *t))
+        .map(|t| vec![t.clone()])
+        .collect::<Vec<_>>();
+    tiers.extend(added);
+    Ok(dedup_tracker_tiers(tiers)
+        .into_iter()
+        .map(|tier| tier.into_iter().map(|t| t.to_string()).collect())
+        .collect())
+}
+
 pub(crate) struct CheckedIncomingConnection {
     pub addr: SocketAddr,
     pub stream: tokio::net::TcpStream,
@@ -475,7 +685,8 @@ pub(crate) struct CheckedIncomingConnection {
 struct InternalAddResult {
     info_hash: Id20,
     metadata: Option<TorrentMetadata>,
-    trackers: Vec<url::Url>,
+    // By tier.
+    trackers: Vec<Vec<url::Url>>,
     name: Option<String>,
 }
 
@@ -511,10 +722,10 @@ impl Session {
 
             let (tcp_listener, tcp_listen_port) =
                 if let Some(port_range) = opts.listen_port_range.clone() {
-                    let (l, p) = create_tcp_listener(port_range)
+                    let (l, p) = create_tcp_listener(port_range, opts.bind.as_ref())
                         .await
                         .context("error listening on TCP")?;
-                    info!("Listening on 0.0.0.0:{p} for incoming peer connections");
+                    log_listening(opts.bind.as_ref(), p);
                     (Some(l), Some(p))
                 } else {
                     (None, None)
@@ -523,15 +734,23 @@ impl Session {
             let dht = if opts.disable_dht {
                 None
             } else {
+                // The DHT port is kept, the address and interface come from the binding.
+                let mut pdht_config = opts.dht_config.take().unwrap_or_default();
+                if let Some(bind) = opts.bind.as_ref() {
+                    let port = pdht_config.listen_addr.map(|a| a.port()).unwrap_or(0);
+                    pdht_config.listen_addr = Some(bind::listen_addr(Some(bind), port));
+                    pdht_config.bind_device = bind.device().map(str::to_owned);
+                }
                 let dht = if opts.disable_dht_persistence {
                     DhtBuilder::with_config(DhtConfig {
+                        listen_addr: pdht_config.listen_addr,
+                        bind_device: pdht_config.bind_device,
                         cancellation_token: Some(token.child_token()),
                         ..Default::default()
                     })
                     .await
                     .context("error initializing DHT")?
                 } else {
-                    let pdht_config = opts.dht_config.take().unwrap_or_default();
                     PersistentDht::create(Some(pdht_config), Some(token.clone()))
                         .await
                         .context("error initializing persistent DHT")?
@@ -606,8 +825,10 @@ impl Session {
                 None => None,
             };
 
-            let reqwest_client = {
-                let builder = if let Some(proxy_url) = opts.socks_proxy_url.as_ref() {
+            let outbound_gate = OutboundGate::default();
+
+            let http_client = |proxy_url: Option<&str>| {
+                let builder = if let Some(proxy_url) = proxy_url {
                     let proxy = reqwest::Proxy::all(proxy_url)
                         .context("error creating socks5 proxy for HTTP")?;
                     reqwest::Client::builder().proxy(proxy)
@@ -615,10 +836,45 @@ impl Session {
                     reqwest::Client::builder()
                 };
 
-                builder.build().context("error building HTTP(S) client")?
+                let builder = match opts.bind.as_ref() {
+                    None => builder,
+                    Some(BindTarget::Address(ip)) => builder.local_address(*ip),
+                    #[cfg(any(target_os = "linux", target_os = "android"))]
+                    Some(BindTarget::Interface(name)) => builder.interface(name),
+                    #[cfg(not(any(target_os = "linux", target_os = "android")))]
+                    Some(BindTarget::Interface(name)) => {
+                        bail!("cannot bind to interface {name}: only supported on Linux")
+                    }
+                };
+
+                builder
+                    .connector_layer(outbound_gate.http_layer())
+                    .build()
+                    .context("error building HTTP(S) client")
             };
 
-            let stream_connector = Arc::new(StreamConnector::from(proxy_config));
+            let reqwest_client = http_client(opts.socks_proxy_url.as_deref())?;
+
+            let (proxy_connector, proxy_reqwest_client) = match opts.torrent_proxy_url.as_ref() {
+                Some(pu) => {
+                    // Not echoing the URL: it may carry a password.
+                    let config =
+                        SocksProxyConfig::parse(pu).context("error parsing torrent proxy url")?;
+                    // socks5h: tracker host names are resolved by the proxy, not locally.
+                    let http_url = pu.replacen("socks5://", "socks5h://", 1);
+                    (
+                        StreamConnector::new(Some(config), outbound_gate.clone(), opts.bind.clone()),
+                        Some(http_client(Some(&http_url))?),
+                    )
+                }
+                None => (StreamConnector::missing_proxy(), None),
+            };
+
+            let stream_connector = Arc::new(StreamConnector::new(
+                proxy_config,
+                outbound_gate.clone(),
+                opts.bind.clone(),
+            ));
 
             let blocklist: blocklist::Blocklist = if let Some(blocklist_url) = opts.blocklist_url {
                 blocklist::Blocklist::load_from_url(&blocklist_url)
@@ -629,9 +885,12 @@ impl Session {
                 blocklist::Blocklist::empty()
             };
 
-            let udp_tracker_client = UdpTrackerClient::new(token.clone())
-                .await
-                .context("error creating UDP tracker client")?;
+            let udp_tracker_client = UdpTrackerClient::with_socket(
+                bind::udp_socket(opts.bind.as_ref(), 0)
+                    .await
+                    .context("error binding UDP for tracker")?,
+                token.clone(),
+            );
 
             let session = Arc::new(Self {
                 persistence,
@@ -645,11 +904,18 @@ impl Session {
                 db: RwLock::new(Default::default()),
                 _cancellation_token_drop_guard: token.clone().drop_guard(),
                 cancellation_token: token,
-                tcp_listen_port,
+                listener: RwLock::new(ListenerState::default()),
+                enable_upnp_port_forwarding: AtomicBool::new(opts.enable_upnp_port_forwarding),
+                dht_peer_discovery: Arc::new(AtomicBool::new(true)),
+                ipv6_peers: AtomicBool::new(true),
+                pex: AtomicBool::new(true),
+                uploads_blocked: AtomicBool::new(false),
+                scraping_blocked: AtomicBool::new(false),
                 disk_write_tx,
                 default_storage_factory: opts.default_storage_factory,
                 reqwest_client,
                 connector: stream_connector,
+                outbound_gate,
                 root_span: opts.root_span,
                 stats: SessionStats::new(),
                 concurrent_initialize_semaphore: Arc::new(tokio::sync::Semaphore::new(
@@ -658,6 +924,9 @@ impl Session {
                 udp_tracker_client,
                 ratelimits: Limits::new(opts.ratelimits),
                 trackers: opts.trackers,
+                bind: opts.bind,
+                proxy_connector: Arc::new(proxy_connector),
+                proxy_reqwest_client,
                 #[cfg(feature = "disable-upload")]
                 _disable_upload: opts.disable_upload,
                 blocklist,
@@ -676,20 +945,8 @@ impl Session {
                 );
             }
 
-            if let Some(tcp_listener) = tcp_listener {
-                session.spawn(
-                    error_span!(parent: session.rs(), "tcp_listen", port = tcp_listen_port),
-                    session.clone().task_tcp_listener(tcp_listener),
-                );
-            }
-
-            if let Some(listen_port) = tcp_listen_port {
-                if opts.enable_upnp_port_forwarding {
-                    session.spawn(
-                        error_span!(parent: session.rs(), "upnp_forward", port = listen_port),
-                        Self::task_upnp_port_forwarder(listen_port),
-                    );
-                }
+            if let (Some(tcp_listener), Some(port)) = (tcp_listener, tcp_listen_port) {
+                session.start_tcp_listener(tcp_listener, port);
             }
 
             if let Some(persistence) = session.persistence.as_ref() {
@@ -729,6 +986,7 @@ impl Session {
             }
 
             session.start_speed_estimator_updater();
+            session.start_tracker_scraper();
 
             Ok(session)
         }
@@ -749,6 +1007,9 @@ impl Session {
         if self.blocklist.is_blocked(incoming_ip) {
             bail!("Incoming ip {incoming_ip} is in blocklist");
         }
+        if !self.peer_ip_family_allowed(incoming_ip) {
+            bail!("Incoming ip {incoming_ip} is IPv6 and IPv6 peers are disabled");
+        }
 
         let mut read_buf = ReadBuf::new();
         let h = read_buf
@@ -766,6 +1027,10 @@ impl Session {
                 continue;
             }
 
+            if torrent.shared().isolation().no_incoming {
+                bail!("torrent {id} does not accept incoming connections");
+            }
+
             let live = match torrent.live() {
                 Some(live) => live,
                 None => {
@@ -829,11 +1094,226 @@ impl Session {
         }
     }
 
-    async fn task_upnp_port_forwarder(port: u16) -> anyhow::Result<()> {
-        let pf = librqbit_upnp::UpnpPortForwarder::new(vec![port], None)?;
+    async fn task_upnp_port_forwarder(pf: UpnpPortForwarder) -> anyhow::Result<()> {
         pf.run_forever().await
     }
 
+    /// Serve incoming peers from `listener`, replacing (and closing) the previous listener.
+    fn start_tcp_listener(self: &Arc<Self>, listener: TcpListener, port: u16) {
+        let cancel = self.cancellation_token.child_token();
+        spawn_with_cancel(
+            error_span!(parent: self.rs(), "tcp_listen", port = port),
+            cancel.clone(),
+            self.clone().task_tcp_listener(listener),
+        );
+
+        let (upnp_cancel, upnp) = if self.enable_upnp_port_forwarding.load(Ordering::Relaxed) {
+            self.start_upnp_forwarder(port, &cancel)
+        } else {
+            (None, None)
+        };
+
+        let previous = std::mem::replace(
+            &mut *self.listener.write(),
+            ListenerState {
+                port: Some(port),
+                cancel: Some(cancel),
+                upnp_cancel,
+                upnp,
+                closed_port: None,
+            },
+        );
+        if let Some(cancel) = previous.cancel {
+            cancel.cancel();
+        }
+    }
+
+    fn start_upnp_forwarder(
+        &self,
+        port: u16,
+        parent: &CancellationToken,
+    ) -> (Option<CancellationToken>, Option<UpnpForwardStatus>) {
+        match UpnpPortForwarder::new(vec![port], None) {
+            Ok(pf) => {
+                let status = pf.status();
+                let cancel = parent.child_token();
+                spawn_with_cancel(
+                    error_span!(parent: self.rs(), "upnp_forward", port = port),
+                    cancel.clone(),
+                    Self::task_upnp_port_forwarder(pf),
+                );
+                (Some(cancel), Some(status))
+            }
+            Err(e) => {
+                warn!("error starting UPnP port forwarder: {e:#}");
+                (None, None)
+            }
+        }
+    }
+
+    /// Start or stop forwarding the listen port via UPnP. Later listen port changes follow
+    /// the new setting.
+    pub fn set_upnp_port_forwarding(&self, enabled: bool) {
+        if self.enable_upnp_port_forwarding.swap(enabled, Ordering::Relaxed) == enabled {
+            return;
+        }
+        let mut listener = self.listener.write();
+        if let Some(cancel) = listener.upnp_cancel.take() {
+            cancel.cancel();
+        }
+        listener.upnp = None;
+        if let (true, Some(port), Some(parent)) = (enabled, listener.port, listener.cancel.clone()) {
+            let (cancel, status) = self.start_upnp_forwarder(port, &parent);
+            listener.upnp_cancel = cancel;
+            listener.upnp = status;
+        }
+        info!(enabled, "UPnP port forwarding changed");
+    }
+
+    /// Allow or stop DHT peer lookups. Torrents started while lookups are off do not use the
+    /// DHT until they are restarted; running torrents drop their DHT lookup as soon as it
+    /// yields another peer after lookups are turned off.
+    pub fn set_dht_peer_discovery(&self, enabled: bool) {
+        self.dht_peer_discovery.store(enabled, Ordering::Relaxed);
+    }
+
+    pub fn dht_peer_discovery(&self) -> bool {
+        self.dht.is_some() && self.dht_peer_discovery.load(Ordering::Relaxed)
+    }
+
+    /// Allow or refuse IPv6 peers, incoming and outgoing. Established connections are kept.
+    pub fn set_ipv6_peers(&self, enabled: bool) {
+        self.ipv6_peers.store(enabled, Ordering::Relaxed);
+    }
+
+    /// Allow or stop peer exchange (BEP 11), both sending and accepting peer lists.
+    pub fn set_pex(&self, enabled: bool) {
+        self.pex.store(enabled, Ordering::Relaxed);
+    }
+
+    pub(crate) fn pex_enabled(&self) -> bool {
+        self.pex.load(Ordering::Relaxed)
+    }
+
+    /// Refuse new outbound connections to peers and trackers (HTTP and UDP). Open
+    /// connections are kept; incoming ones are governed by the listener.
+    pub fn set_outbound_blocked(&self, blocked: bool) {
+        self.outbound_gate.set_blocked(blocked);
+        self.udp_tracker_client.set_blocked(blocked);
+    }
+
+    /// Stop serving pieces to peers; downloading carries on. Live peers are choked until this
+    /// is cleared, and finished torrents stop taking on new peers while it is set.
+    pub fn set_uploads_blocked(&self, blocked: bool) {
+        self.uploads_blocked.store(blocked, Ordering::Relaxed);
+        self.with_torrents(|torrents| {
+            for (_, torrent) in torrents {
+                if let Some(live) = torrent.live() {
+                    live.set_uploads_blocked(blocked);
+                }
+            }
+        });
+    }
+
+    pub(crate) fn uploads_blocked(&self) -> bool {
+        self.uploads_blocked.load(Ordering::Relaxed)
+    }
+
+    /// Stop scraping trackers. Scrapes cover paused torrents too, so pausing torrents alone
+    /// does not keep their info hashes off the network.
+    pub fn set_scraping_blocked(&self, blocked: bool) {
+        self.scraping_blocked.store(blocked, Ordering::Relaxed);
+    }
+
+    pub fn scraping_blocked(&self) -> bool {
+        self.scraping_blocked.load(Ordering::Relaxed)
+    }
+
+    /// Stop accepting incoming peers and drop the UPnP mapping. The port is remembered for
+    /// [`Self::reopen_listener`]; [`Self::rebind_listen_port`] also reopens it.
+    pub fn close_listener(&self) {
+        let mut listener = self.listener.write();
+        let Some(port) = listener.port.take() else {
+            return;
+        };
+        if let Some(cancel) = listener.cancel.take() {
+            cancel.cancel();
+        }
+        listener.upnp_cancel = None;
+        listener.upnp = None;
+        listener.closed_port = Some(port);
+        info!(port, "closed peer listener");
+    }
+
+    /// Listen again on the port closed by [`Self::close_listener`]. No-op if it is open.
+    pub async fn reopen_listener(self: &Arc<Self>) -> anyhow::Result<ListenInfo> {
+        let closed_port = self.listener.read().closed_port;
+        if let Some(port) = closed_port {
+            let listener = bind::tcp_listener(self.bind.as_ref(), port)
+                .with_context(|| format!("error listening on TCP port {port}"))?;
+            self.start_tcp_listener(listener, port);
+            log_listening(self.bind.as_ref(), port);
+        }
+        Ok(self.listen_info())
+    }
+
+    pub(crate) fn peer_ip_family_allowed(&self, ip: IpAddr) -> bool {
+        match ip {
+            IpAddr::V4(_) => true,
+            IpAddr::V6(v6) => {
+                v6.to_ipv4_mapped().is_some() || self.ipv6_peers.load(Ordering::Relaxed)
+            }
+        }
+    }
+
+    /// Move the peer listener (TCP) and the DHT socket (UDP) to `port` without a restart.
+    ///
+    /// Both sockets are bound before the old ones are released, so on error the session keeps
+    /// listening where it was. Running DHT lookups carry over; tracker announces made after this
+    /// call advertise the new port.
+    pub async fn rebind_listen_port(self: &Arc<Self>, port: u16) -> anyhow::Result<ListenInfo> {
+        let current = self.listen_info();
+        let listener = if current.tcp_port == Some(port) {
+            None
+        } else {
+            Some(
+                bind::tcp_listener(self.bind.as_ref(), port)
+                    .with_context(|| format!("error listening on TCP port {port}"))?,
+            )
+        };
+        if let Some(dht) = self.dht.as_ref() {
+            if current.dht_port != Some(port) {
+                dht.rebind(bind::listen_addr(self.bind.as_ref(), port))
+                    .await
+                    .context("error moving DHT socket")?;
+            }
+        }
+        if let Some(listener) = listener {
+            self.start_tcp_listener(listener, port);
+            log_listening(self.bind.as_ref(), port);
+        }
+        Ok(self.listen_info())
+    }
+
+    pub fn listen_info(&self) -> ListenInfo {
+        let listener = self.listener.read();
+        ListenInfo {
+            tcp_port: listener.port,
+            dht_port: self.dht.as_ref().map(|d| d.listen_addr().port()),
+            upnp_enabled: self.enable_upnp_port_forwarding.load(Ordering::Relaxed),
+            upnp_mapped: match (listener.upnp.as_ref(), listener.port) {
+                (Some(status), Some(port)) => status.mapped(port),
+                _ => None,
+            },
+        }
+    }
+
+    /// Local address of the peer listener, if it is open.
+    pub fn tcp_listen_addr(&self) -> Option<SocketAddr> {
+        let port = self.listener.read().port?;
+        Some(bind::listen_addr(self.bind.as_ref(), port))
+    }
+
     pub fn get_dht(&self) -> Option<&Dht> {
         self.dht.as_ref()
     }
@@ -920,11 +1400,11 @@ impl Session {
 
                     InternalAddResult {
                         info_hash,
-                        trackers: magnet
-                            .trackers
-                            .into_iter()
-                            .filter_map(|t| url::Url::parse(&t).ok())
-                            .collect(),
+                        trackers: match opts.tracker_tiers.take() {
+                            Some(tiers) => parse_tracker_tiers(tiers),
+                            // A magnet's trackers are a single tier.
+                            None => parse_tracker_tiers(vec![magnet.trackers]),
+                        },
                         metadata: None,
                         name: magnet.name,
                     }
@@ -947,20 +1427,17 @@ impl Session {
                         }
                     };
 
-                    let mut trackers = torrent
-                        .info
-                        .iter_announce()
-                        .unique()
-                        .filter_map(|tracker| match std::str::from_utf8(tracker.as_ref()) {
-                            Ok(url) => Some(url.to_owned()),
-                            Err(_) => {
-                                warn!("cannot parse tracker url as utf-8, ignoring");
-                                None
-                            }
-                        })
-                        .collect::<Vec<_>>();
+                    let mut trackers = match opts.tracker_tiers.take() {
+                        Some(tiers) => parse_tracker_tiers(tiers),
+                        None => metainfo_tracker_tiers(&torrent.info),
+                    };
                     if let Some(custom_trackers) = opts.trackers.clone() {
-                        trackers.extend(custom_trackers);
+                        trackers.extend(
+                            custom_trackers
+                                .iter()
+                                .filter_map(|t| url::Url::parse(t).ok())
+                                .map(|t| vec![t]),
+                        );
                     }
 
                     InternalAddResult {
@@ -970,10 +1447,7 @@ impl Session {
                             torrent.torrent_bytes,
                             torrent.info_bytes,
                         )?),
-                        trackers: trackers
-                            .iter()
-                            .filter_map(|t| url::Url::parse(t).ok())
-                            .collect(),
+                        trackers,
                         name: None,
                     }
                 }
@@ -1040,17 +1514,21 @@ impl Session {
             trackers,
             name,
         } = add_res;
+        let tracker_urls = trackers.iter().flatten().cloned().collect::<Vec<_>>();
 
         let private = metadata.as_ref().is_some_and(|m| m.info.private);
+        let peer_sources = Arc::new(PeerSources::default());
 
-        let make_peer_rx = || {
+        let make_peer_rx = |private: bool| {
             self.make_peer_rx(
                 info_hash,
+                &peer_sources,
                 trackers.clone(),
                 !opts.paused && !opts.list_only,
                 opts.force_tracker_interval,
                 opts.initial_peers.clone().unwrap_or_default(),
                 private,
+                opts.isolation,
             )
         };
 
@@ -1061,28 +1539,39 @@ impl Session {
                 Some(metadata) => {
                     let mut peer_rx = None;
                     if !opts.paused && !opts.list_only {
-                        peer_rx = make_peer_rx();
+                        peer_rx = make_peer_rx(private);
                     }
                     (metadata, peer_rx)
                 }
                 None => {
-                    let peer_rx = make_peer_rx().context(
+                    let peer_rx = make_peer_rx(private).context(
                         "no known way to resolve peers (no DHT, no trackers, no initial_peers)",
                     )?;
                     let resolved_magnet = self
-                        .resolve_magnet(info_hash, peer_rx, &trackers, opts.peer_opts)
+                        .resolve_magnet(info_hash, peer_rx, &tracker_urls, opts.peer_opts, opts.isolation)
                         .await?;
 
                     // Add back seen_peers into the peer stream, as we consumed some peers
                     // while resolving the magnet.
                     seen_peers = resolved_magnet.seen_peers.clone();
-                    let peer_rx = Some(
-                        merge_streams(
-                            resolved_magnet.peer_rx,
-                            futures::stream::iter(resolved_magnet.seen_peers),
+                    // Only the metainfo says whether the torrent is private. If it is, the
+                    // discovery used to find it (DHT, session trackers) is dropped from here on.
+                    let peer_rx = if resolved_magnet.metadata.info.private {
+                        debug!("magnet resolved to a private torrent, restarting peer discovery");
+                        let seen = futures::stream::iter(resolved_magnet.seen_peers);
+                        Some(match make_peer_rx(true) {
+                            Some(rx) => merge_streams(rx, seen).boxed(),
+                            None => seen.boxed(),
+                        })
+                    } else {
+                        Some(
+                            merge_streams(
+                                resolved_magnet.peer_rx,
+                                futures::stream::iter(resolved_magnet.seen_peers),
+                            )
+                            .boxed(),
                         )
-                        .boxed(),
-                    );
+                    };
                     (resolved_magnet.metadata, peer_rx)
                 }
             }
@@ -1154,7 +1643,7 @@ impl Session {
                 id,
                 span,
                 info_hash,
-                trackers: trackers.into_iter().collect(),
+                trackers: RwLock::new(trackers),
                 spawner: self.spawner,
                 peer_id: self.peer_id,
                 storage_factory,
@@ -1163,15 +1652,18 @@ impl Session {
                     peer_connect_timeout: peer_opts.connect_timeout,
                     peer_read_write_timeout: peer_opts.read_write_timeout,
                     allow_overwrite: opts.overwrite,
-                    output_folder,
+                    output_folder: RwLock::new(output_folder),
                     disk_write_queue: self.disk_write_tx.clone(),
                     ratelimits: opts.ratelimits,
                     initial_peers: opts.initial_peers.clone().unwrap_or_default(),
+                    isolation: RwLock::new(opts.isolation),
                     #[cfg(feature = "disable-upload")]
                     _disable_upload: self._disable_upload,
                 },
                 connector: self.connector.clone(),
+                proxy_connector: self.proxy_connector.clone(),
                 session: Arc::downgrade(self),
+                peer_sources,
                 magnet_name: name,
             });
 
@@ -1187,6 +1679,7 @@ impl Session {
                     paused: opts.paused,
                     state: ManagedTorrentState::Initializing(initializing),
                     only_files,
+                    relocating: false,
                 }),
                 state_change_notify: Notify::new(),
                 shared: minfo,
@@ -1231,6 +1724,20 @@ impl Session {
     }
 
     pub async fn delete(&self, id: TorrentIdOrHash, delete_files: bool) -> anyhow::Result<()> {
+        let files = if delete_files {
+            DeleteFiles::Delete
+        } else {
+            DeleteFiles::Keep
+        };
+        self.delete_with(id, files).await.map(|_| ())
+    }
+
+    /// Remove a torrent from the session and delete, move or keep its files.
+    pub async fn delete_with(
+        &self,
+        id: TorrentIdOrHash,
+        files: DeleteFiles,
+    ) -> anyhow::Result<DeleteStats> {
         let id = match id {
             TorrentIdOrHash::Id(id) => id,
             TorrentIdOrHash::Hash(h) => self
@@ -1247,6 +1754,15 @@ impl Session {
                 })
                 .context("no such torrent in db")?,
         };
+        if self
+            .db
+            .read()
+            .torrents
+            .get(&id)
+            .is_some_and(|t| t.locked.read().relocating)
+        {
+            bail!("torrent is being moved");
+        }
         let removed = self
             .db
             .write()
@@ -1291,27 +1807,27 @@ impl Session {
             }
         }
 
-        match (storage, delete_files) {
-            (Err(e), true) => return Err(e).context("torrent deleted, but could not delete files"),
-            (Ok(storage), true) => {
-                debug!("will delete files");
-                remove_files_and_dirs(&metadata.file_infos, &storage);
-                if removed.shared().options.output_folder != self.output_folder {
+        let output_folder = &removed.shared().output_folder();
+        let stats = match (storage, files) {
+            (_, DeleteFiles::Keep) => {
+                debug!("not deleting files");
+                DeleteStats::default()
+            }
+            (Err(e), _) => return Err(e).context("torrent deleted, but could not delete files"),
+            (Ok(storage), files) => {
+                debug!(?files, "will remove files");
+                let stats = remove_files_and_dirs(&metadata.file_infos, &storage, output_folder, &files);
+                if *output_folder != self.output_folder {
                     if let Err(e) = storage.remove_directory_if_empty(Path::new("")) {
-                        warn!(
-                            "error removing {:?}: {e:#}",
-                            removed.shared().options.output_folder
-                        )
+                        warn!("error removing {:?}: {e:#}", output_folder)
                     }
                 }
-            }
-            (_, false) => {
-                debug!("not deleting files")
+                stats
             }
         };
 
         info!(id, "deleted torrent");
-        Ok(())
+        Ok(stats)
     }
 
     pub fn make_peer_rx_managed_torrent(
@@ -1322,64 +1838,205 @@ impl Session {
         let is_private = t.with_metadata(|m| m.info.private).unwrap_or(false);
         self.make_peer_rx(
             t.info_hash(),
-            t.shared().trackers.iter().cloned().collect(),
+            &t.shared().peer_sources,
+            t.shared().trackers(),
             announce,
             t.shared().options.force_tracker_interval,
             t.shared().options.initial_peers.clone(),
             is_private,
+            t.shared().isolation(),
         )
     }
 
     // Get a peer stream from both DHT and trackers.
+    #[allow(clippy::too_many_arguments)]
     fn make_peer_rx(
         self: &Arc<Self>,
         info_hash: Id20,
-        mut trackers: Vec<url::Url>,
+        peer_sources: &Arc<PeerSources>,
+        trackers: Vec<Vec<url::Url>>,
         announce: bool,
         force_tracker_interval: Option<Duration>,
         initial_peers: Vec<SocketAddr>,
         is_private: bool,
+        isolation: TorrentIsolation,
     ) -> Option<PeerStream> {
-        let announce_port = if announce { self.tcp_listen_port } else { None };
-        let dht_rx = if is_private {
+        let announce_port = if announce && !isolation.no_incoming {
+            self.tcp_listen_port()
+        } else {
+            None
+        };
+        let dht_rx = if is_private
+            || isolation.private
+            || isolation.disable_dht
+            || !self.dht_peer_discovery.load(Ordering::Relaxed)
+        {
             None
         } else {
-            self.dht
-                .as_ref()
-                .map(|dht| dht.get_peers(info_hash, announce_port))
+            self.dht.as_ref().map(|dht| {
+                let allowed = self.dht_peer_discovery.clone();
+                let peer_sources = peer_sources.clone();
+                dht.get_peers(info_hash, announce_port)
+                    .take_while(move |_| std::future::ready(allowed.load(Ordering::Relaxed)))
+                    .inspect(move |_| peer_sources.add_dht_peer())
+            })
         };
 
-        if is_private && trackers.len() > 1 {
-            warn!("private trackers are not fully implemented, so using only the first tracker");
-            trackers.truncate(1);
+        let tracker_rx = self.make_tracker_rx(
+            info_hash,
+            peer_sources,
+            trackers,
+            announce_port,
+            force_tracker_interval,
+            is_private,
+            isolation,
+        );
+
+        let initial_peers_rx = if initial_peers.is_empty() {
+            None
         } else {
-            trackers.extend(self.trackers.iter().cloned());
+            Some(futures::stream::iter(initial_peers))
+        };
+        merge_two_optional_streams(
+            merge_two_optional_streams(dht_rx, tracker_rx),
+            initial_peers_rx,
+        )
+    }
+
+    // Start announcing to the trackers, the tracker half of [`Self::make_peer_rx`].
+    #[allow(clippy::too_many_arguments)]
+    fn make_tracker_rx(
+        self: &Arc<Self>,
+        info_hash: Id20,
+        peer_sources: &Arc<PeerSources>,
+        trackers: Vec<Vec<url::Url>>,
+        announce_port: Option<u16>,
+        force_tracker_interval: Option<Duration>,
+        is_private: bool,
+        isolation: TorrentIsolation,
+    ) -> Option<PeerStream> {
+        if isolation.proxy_trackers && self.proxy_reqwest_client.is_none() && !trackers.is_empty() {
+            warn!("trackers must be proxied but no torrent proxy is configured, not announcing");
         }
+        let (targets, reqwest_client) = self.tracker_targets(trackers, is_private, isolation);
 
         let tracker_rx_stats = PeerRxTorrentInfo {
             info_hash,
             session: self.clone(),
         };
-        let tracker_rx = TrackerComms::start(
+        TrackerComms::start(
             info_hash,
             self.peer_id,
-            trackers.into_iter().collect(),
+            targets,
             Box::new(tracker_rx_stats),
             force_tracker_interval,
             announce_port,
-            self.reqwest_client.clone(),
+            reqwest_client,
             self.udp_tracker_client.clone(),
-        );
+            peer_sources.trackers.clone(),
+        )
+    }
 
-        let initial_peers_rx = if initial_peers.is_empty() {
-            None
-        } else {
-            Some(futures::stream::iter(initial_peers))
+    // The trackers a torrent talks to, and the client for the HTTP ones. The torrent's own
+    // trackers keep their tiers, the session-wide ones are announced to on their own.
+    fn tracker_targets(
+        &self,
+        tiers: Vec<Vec<url::Url>>,
+        is_private: bool,
+        isolation: TorrentIsolation,
+    ) -> (AnnounceTargets, reqwest::Client) {
+        let mut targets = AnnounceTargets {
+            tiers,
+            extra: Vec::new(),
         };
-        merge_two_optional_streams(
-            merge_two_optional_streams(dht_rx, tracker_rx),
-            initial_peers_rx,
-        )
+        // BEP 27: a private torrent announces only to its own trackers.
+        if !is_private && !isolation.private {
+            let extra = self
+                .trackers
+                .iter()
+                .filter(|t| !targets.tiers.iter().flatten().any(|own| own == *t))
+                .cloned()
+                .collect();
+            targets.extra = extra;
+        }
+        if !isolation.proxy_trackers {
+            return (targets, self.reqwest_client.clone());
+        }
+        match self.proxy_reqwest_client.clone() {
+            Some(client) => {
+                let http = |t: &url::Url| {
+                    let http = matches!(t.scheme(), "http" | "https");
+                    if !http {
+                        debug!(tracker = %redact_tracker_url(t), "skipping tracker: only HTTP trackers can be proxied");
+                    }
+                    http
+                };
+                for tier in targets.tiers.iter_mut() {
+                    tier.retain(http);
+                }
+                targets.tiers.retain(|tier| !tier.is_empty());
+                targets.extra.retain(http);
+                (targets, client)
+            }
+            None => (AnnounceTargets::default(), self.reqwest_client.clone()),
+        }
+    }
+
+    /// Scrape the trackers of every torrent, paused ones included, so their swarm counts are
+    /// known before they are started. Trackers whose announces already report counts are left
+    /// alone, and nothing is scraped while [`Self::set_scraping_blocked`] is set.
+    fn start_tracker_scraper(self: &Arc<Self>) {
+        self.spawn(error_span!(parent: self.rs(), "tracker_scraper"), {
+            let s = Arc::downgrade(self);
+
+            async move {
+                let mut i = tokio::time::interval(Duration::from_secs(60));
+                loop {
+                    i.tick().await;
+                    let s = s.upgrade().context("session is dead")?;
+                    futures::stream::iter(s.scrape_jobs())
+                        .for_each_concurrent(SCRAPE_CONCURRENCY, |(info_hash, url, client, states)| {
+                            let s = &s;
+                            async move {
+                                // Blocked while earlier jobs were running.
+                                if s.scraping_blocked() {
+                                    return;
+                                }
+                                if let Err(e) = tracker_comms::scrape_tracker(
+                                    info_hash, &url, &client, &s.udp_tracker_client, &states,
+                                )
+                                .await
+                                {
+                                    debug!(tracker = %redact_tracker_url(&url), ?info_hash, "error scraping: {e:#}");
+                                }
+                            }
+                        })
+                        .await;
+                }
+            }
+        })
+    }
+
+    // The trackers due for a scrape, with the torrent and client to scrape them for.
+    fn scrape_jobs(&self) -> Vec<(Id20, url::Url, reqwest::Client, TrackerStates)> {
+        if self.scraping_blocked() {
+            return Vec::new();
+        }
+        self.with_torrents(|torrents| {
+            let mut jobs = Vec::new();
+            for (_, t) in torrents {
+                let is_private = t.with_metadata(|m| m.info.private).unwrap_or(false);
+                let (targets, client) =
+                    self.tracker_targets(t.shared().trackers(), is_private, t.shared().isolation());
+                let states = t.shared().peer_sources.trackers.clone();
+                for url in targets.urls().cloned() {
+                    if states.scrape_due(&url, SCRAPE_INTERVAL) {
+                        jobs.push((t.info_hash(), url, client.clone(), states.clone()));
+                    }
+                }
+            }
+            jobs
+        })
     }
 
     async fn try_update_persistence_metadata(&self, handle: &ManagedTorrentHandle) {
@@ -1396,6 +2053,12 @@ impl Session {
         Ok(())
     }
 
+    /// Pause without recording it in the session store, so after a restart the torrent comes
+    /// back in whatever state it was last saved in.
+    pub fn pause_transient(&self, handle: &ManagedTorrentHandle) -> anyhow::Result<()> {
+        handle.pause()
+    }
+
     pub async fn unpause(self: &Arc<Self>, handle: &ManagedTorrentHandle) -> anyhow::Result<()> {
         let peer_rx = self.make_peer_rx_managed_torrent(handle, true);
         handle.start(peer_rx, false)?;
@@ -1403,6 +2066,69 @@ impl Session {
         Ok(())
     }
 
+    /// Change how `handle` reaches the network. A live torrent is restarted so its peer
+    /// connections and announces follow the new rules right away; otherwise they apply from
+    /// the next start.
+    pub async fn set_torrent_isolation(
+        self: &Arc<Self>,
+        handle: &ManagedTorrentHandle,
+        isolation: TorrentIsolation,
+    ) -> anyhow::Result<()> {
+        let previous = std::mem::replace(&mut *handle.shared.options.isolation.write(), isolation);
+        if previous == isolation {
+            return Ok(());
+        }
+        if handle.live().is_some() {
+            debug!(id = handle.id(), ?isolation, "restarting torrent under new isolation");
+            handle.pause()?;
+            let peer_rx = self.make_peer_rx_managed_torrent(handle, true);
+            handle.start(peer_rx, false)?;
+        }
+        self.try_update_persistence_metadata(handle).await;
+        Ok(())
+    }
+
+    /// Replace the torrent's own trackers, by BEP 12 tier. A live torrent is not restarted:
+    /// announces to removed trackers stop and new trackers are announced to right away.
+    pub async fn set_torrent_trackers(
+        self: &Arc<Self>,
+        handle: &ManagedTorrentHandle,
+        tiers: Vec<Vec<url::Url>>,
+    ) -> anyhow::Result<()> {
+        *handle.shared.trackers.write() = tiers.into_iter().filter(|t| !t.is_empty()).collect();
+        let is_private = handle.with_metadata(|m| m.info.private).unwrap_or(false);
+        let (targets, _) = self.tracker_targets(
+            handle.shared().trackers(),
+            is_private,
+            handle.shared().isolation(),
+        );
+        let any = !targets.is_empty();
+        let picked_up = handle.shared().peer_sources.trackers.set_trackers(targets);
+        if !picked_up && any && handle.live().is_some() {
+            // It was started without trackers, so no announce loop is running to take them.
+            debug!(id = handle.id(), "starting announces for the new trackers");
+            let isolation = handle.shared().isolation();
+            let announce_port = if isolation.no_incoming {
+                None
+            } else {
+                self.tcp_listen_port()
+            };
+            if let Some(tracker_rx) = self.make_tracker_rx(
+                handle.info_hash(),
+                &handle.shared().peer_sources,
+                handle.shared().trackers(),
+                announce_port,
+                handle.shared().options.force_tracker_interval,
+                is_private,
+                isolation,
+            ) {
+                handle.add_peer_stream(tracker_rx);
+            }
+        }
+        self.try_update_persistence_metadata(handle).await;
+        Ok(())
+    }
+
     pub async fn update_only_files(
         self: &Arc<Self>,
         handle: &ManagedTorrentHandle,
@@ -1413,8 +2139,122 @@ impl Session {
         Ok(())
     }
 
+    /// Move a torrent's files to `output_folder` and point the torrent there.
+    ///
+    /// A live torrent is paused for the move and resumed afterwards with its piece state intact,
+    /// so no recheck is needed. Files are renamed where possible and copied across filesystems;
+    /// if any file fails, everything moved so far is put back and the torrent is left as it was.
+    pub async fn relocate(
+        self: &Arc<Self>,
+        handle: &ManagedTorrentHandle,
+        output_folder: PathBuf,
+        progress: Arc<RelocateProgress>,
+    ) -> anyhow::Result<()> {
+        if !handle.shared().options.allow_overwrite {
+            bail!("torrent was added without overwrite, can't relocate it");
+        }
+        let metadata = handle
+            .metadata
+            .load_full()
+            .context("torrent is not resolved")?;
+        let was_live = {
+            let mut g = handle.locked.write();
+            if g.relocating {
+                bail!("torrent is already being moved");
+            }
+            if let ManagedTorrentState::Initializing(_) = &g.state {
+                bail!("torrent is initializing, can't move it");
+            }
+            let was_live = matches!(g.state, ManagedTorrentState::Live(_));
+            g.relocating = true;
+            was_live
+        };
+        if was_live {
+            if let Err(e) = handle.pause() {
+                handle.locked.write().relocating = false;
+                return Err(e);
+            }
+        }
+
+        let result = self
+            .relocate_paused(handle, metadata, output_folder, progress)
+            .await;
+        handle.locked.write().relocating = false;
+        self.try_update_persistence_metadata(handle).await;
+        if was_live {
+            if let Err(e) = self.unpause(handle).await {
+                warn!(id = handle.id(), "error resuming torrent after move: {e:#}");
+            }
+        }
+        result
+    }
+
+    async fn relocate_paused(
+        &self,
+        handle: &ManagedTorrentHandle,
+        metadata: Arc<TorrentMetadata>,
+        to: PathBuf,
+        progress: Arc<RelocateProgress>,
+    ) -> anyhow::Result<()> {
+        let from = handle.shared().output_folder();
+        if from == to {
+            return Ok(());
+        }
+        let files = metadata
+            .file_infos
+            .iter()
+            .filter(|fi| !fi.attrs.padding)
+            .map(|fi| fi.relative_filename.clone())
+            .collect::<Vec<_>>();
+
+        {
+            let (files, from, to) = (files.clone(), from.clone(), to.clone());
+            tokio::task::spawn_blocking(move || move_files(&files, &from, &to, &progress))
+                .await
+                .context("move task panicked")??;
+        }
+
+        // Reopen the files at their new location; the chunk tracker stays as it was.
+        *handle.shared().options.output_folder.write() = to.clone();
+        let reopened = handle.with_state_mut(|s| match s.take() {
+            ManagedTorrentState::Paused(p) => {
+                match p.shared.storage_factory.create_and_init(&p.shared, &metadata) {
+                    Ok(files) => {
+                        *s = ManagedTorrentState::Paused(TorrentStatePaused { files, ..p });
+                        Ok(())
+                    }
+                    Err(e) => {
+                        *s = ManagedTorrentState::Paused(p);
+                        Err(e)
+                    }
+                }
+            }
+            other => {
+                *s = other;
+                Ok(())
+            }
+        });
+        if let Err(e) = reopened {
+            *handle.shared().options.output_folder.write() = from.clone();
+            let undo = tokio::task::spawn_blocking(move || {
+                move_files(&files, &to, &from, &RelocateProgress::default())
+            })
+            .await;
+            if !matches!(undo, Ok(Ok(()))) {
+                error!(id = handle.id(), "could not move files back after a failed relocation");
+            }
+            return Err(e).context("error opening moved files");
+        }
+        if from != self.output_folder {
+            // Only succeeds if the torrent's old folder is now empty.
+            let _ = std::fs::remove_dir(&from);
+        }
+        info!(id = handle.id(), ?to, "moved torrent");
+        Ok(())
+    }
+
     pub fn tcp_listen_port(&self) -> Option<u16> {
-        self.tcp_listen_port
+        self.listener.read().port
     }
 
     async fn resolve_magnet(
@@ -1423,14 +2263,20 @@ impl Session {
         peer_rx: PeerStream,
         trackers: &[url::Url],
         peer_opts: Option<PeerConnectionOptions>,
+        isolation: TorrentIsolation,
     ) -> anyhow::Result<ResolveMagnetResult> {
+        let connector = if isolation.proxy_peers {
+            self.proxy_connector.clone()
+        } else {
+            self.connector.clone()
+        };
         match read_metainfo_from_peer_receiver(
             self.peer_id,
             info_hash,
             Default::default(),
             peer_rx,
             Some(self.merge_peer_opts(peer_opts)),
-            self.connector.clone(),
+            connector,
         )
         .await
         {
@@ -1470,17 +2316,164 @@ pub(crate) struct ResolveMagnetResult {
     pub seen_peers: Vec<SocketAddr>,
 }
 
-fn remove_files_and_dirs(infos: &FileInfos, files: &dyn TorrentStorage) {
+/// Move one file out of the output folder, copying when it is on another filesystem.
+fn move_file(from: &Path, to: &Path) -> anyhow::Result<()> {
+    if let Some(parent) = to.parent() {
+        std::fs::create_dir_all(parent).with_context(|| format!("error creating {parent:?}"))?;
+    }
+    if std::fs::rename(from, to).is_ok() {
+        return Ok(());
+    }
+    std::fs::copy(from, to).with_context(|| format!("error copying {from:?} to {to:?}"))?;
+    Ok(())
+}
+
+/// Copy `from` to `to` in chunks, counting bytes into `moved`.
+fn copy_with_progress(from: &Path, to: &Path, moved: &AtomicU64) -> anyhow::Result<()> {
+    use std::io::Write;
+    let mut src = std::fs::File::open(from).with_context(|| format!("error opening {from:?}"))?;
+    let mut dst = std::fs::OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(to)
+        .with_context(|| format!("error creating {to:?}"))?;
+    let mut buf = vec![0u8; 1024 * 1024];
+    loop {
+        let n = src.read(&mut buf).with_context(|| format!("error reading {from:?}"))?;
+        if n == 0 {
+            break;
+        }
+        dst.write_all(&buf[..n])
+            .with_context(|| format!("error writing {to:?}"))?;
+        moved.fetch_add(n as u64, Ordering::Relaxed);
+    }
+    dst.sync_all()?;
+    if let Ok(meta) = std::fs::metadata(from) {
+        let _ = std::fs::set_permissions(to, meta.permissions());
+    }
+    Ok(())
+}
+
+/// Move torrent files between output folders. Sources of copied files are only removed once
+/// every file made it, so a failure can be rolled back completely.
+fn move_files(
+    files: &[PathBuf],
+    from: &Path,
+    to: &Path,
+    progress: &RelocateProgress,
+) -> anyhow::Result<()> {
+    let mut present = Vec::new();
+    for f in files {
+        let Ok(meta) = std::fs::metadata(from.join(f)) else {
+            // Not downloaded yet (e.g. skipped files); it will be created at the new place.
+            continue;
+        };
+        if to.join(f).exists() {
+            bail!("{:?} already exists", to.join(f));
+        }
+        progress.total_bytes.fetch_add(meta.len(), Ordering::Relaxed);
+        present.push((f, meta.len()));
+    }
+
+    // (file, was renamed)
+    let mut done: Vec<(&PathBuf, bool)> = Vec::new();
+    let mut result = Ok(());
+    for (f, len) in present {
+        let (src, dst) = (from.join(f), to.join(f));
+        if let Some(parent) = dst.parent() {
+            if let Err(e) = std::fs::create_dir_all(parent) {
+                result = Err(e).with_context(|| format!("error creating {parent:?}"));
+                break;
+            }
+        }
+        if std::fs::rename(&src, &dst).is_ok() {
+            progress.moved_bytes.fetch_add(len, Ordering::Relaxed);
+            done.push((f, true));
+            continue;
+        }
+        if let Err(e) = copy_with_progress(&src, &dst, &progress.moved_bytes) {
+            let _ = std::fs::remove_file(&dst);
+            result = Err(e);
+            break;
+        }
+        done.push((f, false));
+    }
+
+    if let Err(e) = result {
+        for (f, renamed) in done.iter().rev() {
+            let (src, dst) = (from.join(f), to.join(f));
+            let undo = if *renamed {
+                std::fs::rename(&dst, &src)
+            } else {
+                std::fs::remove_file(&dst)
+            };
+            if let Err(e) = undo {
+                warn!(?f, error=?e, "error rolling back moved file");
+            }
+        }
+        remove_empty_parents(files, to);
+        return Err(e);
+    }
+
+    for (f, renamed) in done {
+        if !renamed {
+            if let Err(e) = std::fs::remove_file(from.join(f)) {
+                warn!(?f, error=?e, "error removing original after copy");
+            }
+        }
+    }
+    remove_empty_parents(files, from);
+    Ok(())
+}
+
+/// Remove the torrent's sub-directories under `root` that are now empty, deepest first.
+fn remove_empty_parents(files: &[PathBuf], root: &Path) {
+    let mut dirs = files
+        .iter()
+        .flat_map(|f| f.ancestors().skip(1))
+        .filter(|d| *d != Path::new(""))
+        .collect::<HashSet<_>>()
+        .into_iter()
+        .collect::<Vec<_>>();
+    dirs.sort_unstable_by_key(|p| std::cmp::Reverse(p.as_os_str().len()));
+    for dir in dirs {
+        // Fails on non-empty directories, which is what we want.
+        let _ = std::fs::remove_dir(root.join(dir));
+    }
+}
+
+fn remove_files_and_dirs(
+    infos: &FileInfos,
+    files: &dyn TorrentStorage,
+    output_folder: &Path,
+    mode: &DeleteFiles,
+) -> DeleteStats {
+    let mut stats = DeleteStats::default();
     let mut all_dirs = HashSet::new();
     for (id, fi) in infos.iter().enumerate() {
         if fi.attrs.padding {
             continue;
         }
         let mut fname = &*fi.relative_filename;
-        if let Err(e) = files.remove_file(id, fname) {
-            warn!(?fi.relative_filename, error=?e, "could not delete file");
+        let src = output_folder.join(fname);
+        let len = std::fs::metadata(&src).map(|m| m.len()).unwrap_or(0);
+        let result = match mode {
+            DeleteFiles::Keep => Ok(()),
+            DeleteFiles::Delete => files.remove_file(id, fname),
+            DeleteFiles::MoveTo(dest) => move_file(&src, &dest.join(fname)).and_then(|()| {
+                // rename() already took it away; after a copy the original is still there.
+                match files.remove_file(id, fname) {
+                    Err(_) if !src.exists() => Ok(()),
+                    r => r,
+                }
+            }),
+        };
+        if let Err(e) = result {
+            warn!(?fi.relative_filename, error=?e, "could not remove file");
         } else {
-            debug!(?fi.relative_filename, "deleted the file")
+            debug!(?fi.relative_filename, "removed the file");
+            stats.files += 1;
+            stats.bytes += len;
         }
         while let Some(parent) = fname.parent() {
             if parent != Path::new("") {
@@ -1502,6 +2495,7 @@ fn remove_files_and_dirs(infos: &FileInfos, files: &dyn TorrentStorage) {
             debug!("removed {dir:?}")
         }
     }
+    stats
 }
 
 // Ad adapter for converting stats into the format that tracker_comms accepts.
@@ -1552,7 +2546,241 @@ mod tests {
     use itertools::Itertools;
     use librqbit_core::torrent_metainfo::{torrent_from_bytes_ext, TorrentMetaV1};
 
-    use super::torrent_file_from_info_bytes;
+    use std::path::{Path, PathBuf};
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    use anyhow::Context;
+    use bytes::Bytes;
+
+    use super::{
+        move_files, torrent_file_from_info_bytes, DeleteFiles, DeleteStats, ManagedTorrentHandle,
+        RelocateProgress,
+    };
+    use crate::{
+        api::TorrentIdOrHash, create_torrent, tests::test_util::wait_until, AddTorrent,
+        AddTorrentOptions, CreateTorrentOptions, Session, SessionOptions, SessionPersistenceConfig,
+    };
+
+    /// A session in a temp folder, and a two-file torrent of the files in its `data` folder.
+    async fn session_with_data(prefix: &str) -> (tempfile::TempDir, Arc<Session>, Bytes) {
+        let dir = tempfile::TempDir::with_prefix(prefix).unwrap();
+        let data = dir.path().join("data");
+        std::fs::create_dir_all(data.join("sub")).unwrap();
+        std::fs::write(data.join("a"), b"aaaa").unwrap();
+        std::fs::write(data.join("sub/b"), b"bb").unwrap();
+        let torrent = create_torrent(&data, CreateTorrentOptions::default())
+            .await
+            .unwrap();
+
+        let session = Session::new_with_opts(
+            dir.path().join("session"),
+            SessionOptions {
+                disable_dht: true,
+                disable_dht_persistence: true,
+                ..Default::default()
+            },
+        )
+        .await
+        .unwrap();
+        (dir, session, torrent.as_bytes().unwrap())
+    }
+
+    async fn add_data_torrent(
+        session: &Arc<Session>,
+        dir: &Path,
+        torrent: Bytes,
+        paused: bool,
+    ) -> ManagedTorrentHandle {
+        session
+            .add_torrent(
+                AddTorrent::TorrentFileBytes(torrent),
+                Some(AddTorrentOptions {
+                    paused,
+                    overwrite: true,
+                    output_folder: Some(dir.join("data").to_str().unwrap().to_owned()),
+                    ..Default::default()
+                }),
+            )
+            .await
+            .unwrap()
+            .into_handle()
+            .unwrap()
+    }
+
+    /// Add a paused two-file torrent seeded from a temp folder and remove it with `files`.
+    async fn delete_with_files(
+        files: impl FnOnce(&Path) -> DeleteFiles,
+    ) -> (tempfile::TempDir, DeleteStats) {
+        let (dir, session, torrent) = session_with_data("rqbit-delete").await;
+        let handle = add_data_torrent(&session, dir.path(), torrent, true).await;
+
+        let stats = session
+            .delete_with(TorrentIdOrHash::Id(handle.id()), files(dir.path()))
+            .await
+            .unwrap();
+        assert!(session.get(TorrentIdOrHash::Id(handle.id())).is_none());
+        (dir, stats)
+    }
+
+    #[tokio::test]
+    async fn test_delete_with_removes_files() {
+        let (dir, stats) = delete_with_files(|_| DeleteFiles::Delete).await;
+        assert_eq!(stats, DeleteStats { files: 2, bytes: 6 });
+        assert!(!dir.path().join("data/a").exists());
+        assert!(!dir.path().join("data/sub").exists());
+    }
+
+    #[tokio::test]
+    async fn test_delete_with_moves_files_to_trash() {
+        let (dir, stats) = delete_with_files(|root| DeleteFiles::MoveTo(root.join("trash"))).await;
+        assert_eq!(stats, DeleteStats { files: 2, bytes: 6 });
+        assert!(!dir.path().join("data/a").exists());
+        assert_eq!(std::fs::read(dir.path().join("trash/a")).unwrap(), b"aaaa");
+        assert_eq!(
+            std::fs::read(dir.path().join("trash/sub/b")).unwrap(),
+            b"bb"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_trackers_added_to_live_torrent_are_announced_without_restart() {
+        let (dir, session, torrent) = session_with_data("rqbit-trackers").await;
+        let handle = add_data_torrent(&session, dir.path(), torrent, false).await;
+        wait_until(
+            || handle.live().map(|_| ()).context("not live yet"),
+            Duration::from_secs(10),
+        )
+        .await
+        .unwrap();
+        let live = handle.live().unwrap();
+
+        let tracker = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/announce", tracker.local_addr().unwrap());
+        session
+            .set_torrent_trackers(&handle, vec![vec![url.parse().unwrap()]])
+            .await
+            .unwrap();
+        tokio::time::timeout(Duration::from_secs(10), tracker.accept())
+            .await
+            .expect("the new tracker is announced to")
+            .unwrap();
+        assert!(Arc::ptr_eq(&live, &handle.live().unwrap()));
+    }
+
+    #[tokio::test]
+    async fn test_untiered_store_restores_metainfo_tiers() {
+        let dir = tempfile::TempDir::with_prefix("rqbit-retier").unwrap();
+        let tracker = |host: &str| format!("udp://{host}.example:1/announce");
+        let (a, b, c, d) = (tracker("a"), tracker("b"), tracker("c"), tracker("d"));
+        let bstr = |s: &str| format!("{}:{s}", s.len());
+        let mut torrent = format!(
+            "d8:announce{}13:announce-listll{}{}el{}ee4:infod6:lengthi4e4:name1:a12:piece lengthi16384e6:pieces20:",
+            bstr(&a),
+            bstr(&a),
+            bstr(&b),
+            bstr(&c)
+        )
+        .into_bytes();
+        torrent.extend([0u8; 20]);
+        torrent.extend(b"ee");
+        let info_hash = super::torrent_from_bytes(torrent.clone().into())
+            .unwrap()
+            .info
+            .info_hash;
+
+        let store = dir.path().join("store");
+        std::fs::create_dir_all(&store).unwrap();
+        std::fs::write(store.join(format!("{info_hash:?}.torrent")), &torrent).unwrap();
+        // One tier per tracker, as stores written before `tiered` have them, with `d` added.
+        let db = serde_json::json!({"torrents": {"0": {
+            "info_hash": info_hash.as_string(),
+            "trackers": [[&c], [&a], [&b], [&d]],
+            "output_folder": dir.path().join("data"),
+            "only_files": null,
+            "is_paused": true,
+        }}});
+        std::fs::write(store.join("session.json"), db.to_string()).unwrap();
+
+        let session = Session::new_with_opts(
+            dir.path().join("session"),
+            SessionOptions {
+                disable_dht: true,
+                disable_dht_persistence: true,
+                persistence: Some(SessionPersistenceConfig::Json {
+                    folder: Some(store),
+                }),
+                ..Default::default()
+            },
+        )
+        .await
+        .unwrap();
+        let handle = session.get(TorrentIdOrHash::Id(0)).unwrap();
+        let url = |t: &str| url::Url::parse(t).unwrap();
+        assert_eq!(
+            handle.shared().trackers(),
+            vec![vec![url(&a), url(&b)], vec![url(&c)], vec![url(&d)]]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_scraping_blocked_skips_paused_torrents() {
+        let dir = tempfile::TempDir::with_prefix("rqbit-scrape").unwrap();
+        let session = Session::new_with_opts(
+            dir.path().join("session"),
+            SessionOptions {
+                disable_dht: true,
+                disable_dht_persistence: true,
+                ..Default::default()
+            },
+        )
+        .await
+        .unwrap();
+        session
+            .add_torrent(
+                AddTorrent::from_bytes(
+                    &include_bytes!("../resources/ubuntu-21.04-desktop-amd64.iso.torrent")[..],
+                ),
+                Some(AddTorrentOptions {
+                    paused: true,
+                    output_folder: Some(dir.path().join("data").to_str().unwrap().to_owned()),
+                    ..Default::default()
+                }),
+            )
+            .await
+            .unwrap();
+        assert!(!session.scrape_jobs().is_empty());
+
+        session.set_scraping_blocked(true);
+        assert!(session.scrape_jobs().is_empty());
+        session.set_scraping_blocked(false);
+        assert!(!session.scrape_jobs().is_empty());
+    }
+
+    #[test]
+    fn test_move_files_rolls_back_on_failure() {
+        let root = std::env::temp_dir().join(format!("rqbit-move-{}", std::process::id()));
+        let (from, to) = (root.join("from"), root.join("to"));
+        std::fs::create_dir_all(from.join("sub")).unwrap();
+        std::fs::create_dir_all(&to).unwrap();
+        std::fs::write(from.join("a"), b"aaaa").unwrap();
+        std::fs::write(from.join("sub/b"), b"bb").unwrap();
+        // A file where the directory should go makes the second file fail.
+        std::fs::write(to.join("sub"), b"").unwrap();
+
+        let files = [PathBuf::from("a"), PathBuf::from("sub/b")];
+        assert!(move_files(&files, &from, &to, &RelocateProgress::default()).is_err());
+        assert!(from.join("a").exists() && from.join("sub/b").exists());
+        assert!(!to.join("a").exists());
+
+        std::fs::remove_file(to.join("sub")).unwrap();
+        let progress = RelocateProgress::default();
+        move_files(&files, &from, &to, &progress).unwrap();
+        assert!(to.join("a").exists() && to.join("sub/b").exists());
+        assert!(!from.join("sub").exists());
+        assert_eq!(progress.moved_bytes.into_inner(), 6);
+        std::fs::remove_dir_all(&root).unwrap();
+    }
 
     #[test]
     fn test_torrent_file_from_info_and_bytes() {
@@ -138,16 +138,18 @@ impl JsonSessionPersistenceStore {
         let st = SerializedTorrent {
             trackers: torrent
                 .shared()
-                .trackers
+                .trackers()
                 .iter()
-                .map(|u| u.to_string())
+                .map(|tier| tier.iter().map(|u| u.to_string()).collect())
                 .collect(),
+            tiered: true,
             info_hash: torrent.info_hash(),
             // we don't serialize this here, but to a file instead.
             torrent_bytes: Default::default(),
             only_files: torrent.only_files().clone(),
             is_paused: torrent.is_paused(),
-            output_folder: torrent.shared().options.output_folder.clone(),
+            output_folder: torrent.shared().output_folder(),
+            isolation: torrent.shared().isolation(),
         };
 
         let torrent_bytes = torrent
@@ -2,7 +2,7 @@ pub mod json;
 #[cfg(feature = "postgres")]
 pub mod postgres;
 
-use std::{collections::HashSet, path::PathBuf};
+use std::path::PathBuf;
 
 use anyhow::Context;
 use async_trait::async_trait;
@@ -13,8 +13,10 @@ use librqbit_core::Id20;
 use serde::{Deserialize, Deserializer, Serialize, Serializer};
 
 use crate::{
-    bitv_factory::BitVFactory, session::TorrentId, torrent_state::ManagedTorrentHandle, AddTorrent,
-    AddTorrentOptions,
+    bitv_factory::BitVFactory,
+    session::{retier_stored_trackers, TorrentId, TorrentIsolation},
+    torrent_state::ManagedTorrentHandle,
+    AddTorrent, AddTorrentOptions,
 };
 
 #[derive(Serialize, Deserialize, Clone)]
@@ -26,10 +28,18 @@ pub struct SerializedTorrent {
     info_hash: Id20,
     #[serde(skip)]
     torrent_bytes: Bytes,
-    trackers: HashSet<String>,
+    // By tier. Older stores kept a flat set.
+    #[serde(deserialize_with = "deserialize_tracker_tiers")]
+    trackers: Vec<Vec<String>>,
+    // Whether `trackers` holds the torrent's own tiers. Stores written before it was added
+    // have flat sets or one tier per tracker; those are put back into the metainfo's tiers.
+    #[serde(default)]
+    tiered: bool,
     output_folder: PathBuf,
     only_files: Option<Vec<usize>>,
     is_paused: bool,
+    #[serde(default)]
+    isolation: TorrentIsolation,
 }
 
 impl SerializedTorrent {
@@ -37,12 +47,21 @@ impl SerializedTorrent {
         &self.info_hash
     }
     pub fn into_add_torrent(self) -> anyhow::Result<(AddTorrent<'static>, AddTorrentOptions)> {
+        let trackers = if self.tiered {
+            self.trackers.clone()
+        } else if self.torrent_bytes.is_empty() {
+            // A magnet's trackers are a single tier.
+            vec![self.trackers.iter().flatten().cloned().collect()]
+        } else {
+            retier_stored_trackers(&self.trackers, self.torrent_bytes.clone())
+                .context("error decoding stored torrent")?
+        };
         let add_torrent = if !self.torrent_bytes.is_empty() {
             AddTorrent::TorrentFileBytes(self.torrent_bytes)
         } else {
             let magnet = Magnet::from_id20(
                 self.info_hash,
-                self.trackers.into_iter().collect(),
+                self.trackers.iter().flatten().cloned().collect(),
                 self.only_files.clone(),
             )
             .to_string();
@@ -59,6 +78,8 @@ impl SerializedTorrent {
             ),
             only_files: self.only_files,
             overwrite: true,
+            isolation: self.isolation,
+            tracker_tiers: Some(trackers),
             ..Default::default()
         };
 
@@ -96,3 +117,19 @@ where
 {
     Id20::deserialize(deserializer)
 }
+
+fn deserialize_tracker_tiers<'de, D>(deserializer: D) -> Result<Vec<Vec<String>>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Trackers {
+        Tiers(Vec<Vec<String>>),
+        Flat(Vec<String>),
+    }
+    Ok(match Trackers::deserialize(deserializer)? {
+        Trackers::Tiers(tiers) => tiers,
+        Trackers::Flat(urls) => urls.into_iter().map(|u| vec![u]).collect(),
+    })
+}
@@ -35,12 +35,16 @@ impl TorrentsTableRecord {
             SerializedTorrent {
                 info_hash: Id20::from_bytes(&self.info_hash).ok()?,
                 torrent_bytes: self.torrent_bytes.into(),
-                trackers: self.trackers.into_iter().collect(),
+                // Stored flat; restoring puts them back into the metainfo's tiers.
+                trackers: vec![self.trackers],
+                tiered: false,
                 output_folder: PathBuf::from(self.output_folder),
                 only_files: self
                     .only_files
                     .map(|v| v.into_iter().map(|v| v as usize).collect()),
                 is_paused: self.is_paused,
+                // Not stored in postgres; restored torrents start unrestricted.
+                isolation: Default::default(),
             },
         ))
     }
@@ -112,7 +116,7 @@ impl SessionPersistenceStore for PostgresSessionStorage {
             .bind(
                 torrent
                     .shared()
-                    .trackers
+                    .tracker_urls()
                     .iter()
                     .map(|t| t.to_string())
                     .collect::<Vec<_>>(),
@@ -120,8 +124,7 @@ impl SessionPersistenceStore for PostgresSessionStorage {
             .bind(
                 torrent
                     .shared()
-                    .options
-                    .output_folder
+                    .output_folder()
                     .to_str()
                     .context("output_folder")?
                     .to_owned(),
@@ -163,17 +166,27 @@ impl SessionPersistenceStore for PostgresSessionStorage {
         id: TorrentId,
         torrent: &ManagedTorrentHandle,
     ) -> anyhow::Result<()> {
-        sqlx::query("UPDATE torrents SET only_files = $1, is_paused = $2 WHERE id = $3")
-            .bind(torrent.only_files().map(|v| {
-                v.into_iter()
-                    .filter_map(|f| f.try_into().ok())
-                    .collect::<Vec<i32>>()
-            }))
-            .bind(torrent.is_paused())
-            .bind::<i32>(id.try_into()?)
-            .execute(&self.pool)
-            .await
-            .context("error executing UPDATE torrents")?;
+        sqlx::query(
+            "UPDATE torrents SET only_files = $1, is_paused = $2, trackers = $3 WHERE id = $4",
+        )
+        .bind(torrent.only_files().map(|v| {
+            v.into_iter()
+                .filter_map(|f| f.try_into().ok())
+                .collect::<Vec<i32>>()
+        }))
+        .bind(torrent.is_paused())
+        .bind(
+            torrent
+                .shared()
+                .tracker_urls()
+                .iter()
+                .map(|t| t.to_string())
+                .collect::<Vec<_>>(),
+        )
+        .bind::<i32>(id.try_into()?)
+        .execute(&self.pool)
+        .await
+        .context("error executing UPDATE torrents")?;
         Ok(())
     }
 
@@ -1,6 +1,11 @@
 use std::net::SocketAddr;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
 
 use anyhow::Context;
+use tokio::net::TcpStream;
+
+use crate::bind::{self, BindTarget};
 
 #[derive(Debug, Clone)]
 pub(crate) struct SocksProxyConfig {
@@ -30,43 +35,134 @@ impl SocksProxyConfig {
     async fn connect(
         &self,
         addr: SocketAddr,
+        bind: Option<&BindTarget>,
     ) -> anyhow::Result<(
         impl tokio::io::AsyncRead + Unpin,
         impl tokio::io::AsyncWrite + Unpin,
     )> {
         let proxy_addr = (self.host.as_str(), self.port);
 
-        let stream = if let Some((username, password)) = self.username_password.as_ref() {
-            tokio_socks::tcp::Socks5Stream::connect_with_password(
-                proxy_addr,
-                addr,
-                username.as_str(),
-                password.as_str(),
-            )
-            .await
-            .context("error connecting to proxy")?
-        } else {
-            tokio_socks::tcp::Socks5Stream::connect(proxy_addr, addr)
-                .await
-                .context("error connecting to proxy")?
+        let stream = match bind {
+            // The proxy is reached from the bound interface like everything else.
+            Some(bind) => {
+                let socket = self.connect_bound(bind).await?;
+                if let Some((username, password)) = self.username_password.as_ref() {
+                    tokio_socks::tcp::Socks5Stream::connect_with_password_and_socket(
+                        socket,
+                        addr,
+                        username.as_str(),
+                        password.as_str(),
+                    )
+                    .await
+                    .context("error connecting to proxy")?
+                } else {
+                    tokio_socks::tcp::Socks5Stream::connect_with_socket(socket, addr)
+                        .await
+                        .context("error connecting to proxy")?
+                }
+            }
+            None => {
+                if let Some((username, password)) = self.username_password.as_ref() {
+                    tokio_socks::tcp::Socks5Stream::connect_with_password(
+                        proxy_addr,
+                        addr,
+                        username.as_str(),
+                        password.as_str(),
+                    )
+                    .await
+                    .context("error connecting to proxy")?
+                } else {
+                    tokio_socks::tcp::Socks5Stream::connect(proxy_addr, addr)
+                        .await
+                        .context("error connecting to proxy")?
+                }
+            }
         };
 
         Ok(tokio::io::split(stream))
     }
+
+    async fn connect_bound(&self, bind: &BindTarget) -> anyhow::Result<TcpStream> {
+        let mut last_error = None;
+        for addr in tokio::net::lookup_host((self.host.as_str(), self.port))
+            .await
+            .context("error resolving proxy host")?
+        {
+            let attempt = async {
+                bind::outbound_tcp_socket(bind, addr)?
+                    .connect(addr)
+                    .await
+                    .context("error connecting to proxy")
+            };
+            match attempt.await {
+                Ok(stream) => return Ok(stream),
+                Err(e) => last_error = Some(e),
+            }
+        }
+        Err(last_error.unwrap_or_else(|| anyhow::anyhow!("proxy host resolved to no addresses")))
+    }
+}
+
+/// Refuses new outbound connections while blocked. Shared by [`StreamConnector`] (peers) and
+/// the session's HTTP client (trackers, torrent downloads); connections already open are kept.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct OutboundGate(Arc<AtomicBool>);
+
+impl OutboundGate {
+    pub fn set_blocked(&self, blocked: bool) {
+        self.0.store(blocked, Ordering::Relaxed);
+    }
+
+    pub fn is_blocked(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+
+    /// Layer for `reqwest::ClientBuilder::connector_layer` that fails connects while blocked.
+    pub fn http_layer<R>(
+        &self,
+    ) -> tower::filter::FilterLayer<impl Fn(R) -> Result<R, std::io::Error> + Clone> {
+        let gate = self.clone();
+        tower::filter::FilterLayer::new(move |req| {
+            if gate.is_blocked() {
+                Err(std::io::Error::other("outbound connections are blocked"))
+            } else {
+                Ok(req)
+            }
+        })
+    }
 }
 
 #[derive(Debug, Default)]
 pub(crate) struct StreamConnector {
     proxy_config: Option<SocksProxyConfig>,
+    gate: OutboundGate,
+    bind: Option<BindTarget>,
+    // Stands in for a proxy that is required but not configured: connects nowhere.
+    refuse: bool,
 }
 
-impl From<Option<SocksProxyConfig>> for StreamConnector {
-    fn from(proxy_config: Option<SocksProxyConfig>) -> Self {
-        Self { proxy_config }
+impl StreamConnector {
+    pub fn new(
+        proxy_config: Option<SocksProxyConfig>,
+        gate: OutboundGate,
+        bind: Option<BindTarget>,
+    ) -> Self {
+        Self {
+            proxy_config,
+            gate,
+            bind,
+            refuse: false,
+        }
+    }
+
+    /// For connections that must go through a proxy when none is configured.
+    pub fn missing_proxy() -> Self {
+        Self {
+            refuse: true,
+            ..Default::default()
+        }
     }
-}
 
-impl StreamConnector {
     pub async fn connect(
         &self,
         addr: SocketAddr,
@@ -74,15 +170,22 @@ impl StreamConnector {
         Box<dyn tokio::io::AsyncRead + Send + Unpin>,
         Box<dyn tokio::io::AsyncWrite + Send + Unpin>,
     )> {
+        if self.gate.is_blocked() {
+            anyhow::bail!("outbound connections are blocked");
+        }
+        if self.refuse {
+            anyhow::bail!("no proxy is configured for this torrent's connections");
+        }
         if let Some(proxy) = self.proxy_config.as_ref() {
-            let (r, w) = proxy.connect(addr).await?;
+            let (r, w) = proxy.connect(addr, self.bind.as_ref()).await?;
             return Ok((Box::new(r), Box::new(w)));
         }
 
-        let (r, w) = tokio::net::TcpStream::connect(addr)
-            .await
-            .context("error connecting")?
-            .into_split();
+        let stream = match self.bind.as_ref() {
+            Some(bind) => bind::outbound_tcp_socket(bind, addr)?.connect(addr).await,
+            None => TcpStream::connect(addr).await,
+        };
+        let (r, w) = stream.context("error connecting")?.into_split();
         Ok((Box::new(r), Box::new(w)))
     }
 }
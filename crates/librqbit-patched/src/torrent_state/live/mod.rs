@@ -462,7 +462,7 @@ impl TorrentStateLive {
             &handler,
             Some(options),
             self.shared.spawner,
-            self.shared.connector.clone(),
+            self.shared.peer_connector(),
         );
         let requester = handler.task_peer_chunk_requester();
 
@@ -527,7 +527,7 @@ impl TorrentStateLive {
             &handler,
             Some(options),
             state.shared.spawner,
-            state.shared.connector.clone(),
+            state.shared.peer_connector(),
         );
         let requester = aframe!(handler
             .task_peer_chunk_requester()
@@ -566,7 +566,9 @@ impl TorrentStateLive {
         let state = self;
         loop {
             let addr = peer_queue_rx.recv().await.context("torrent closed")?;
-            if state.shared.options.disable_upload() && state.is_finished_and_no_active_streams() {
+            if (state.shared.options.disable_upload() || state.uploads_blocked())
+                && state.is_finished_and_no_active_streams()
+            {
                 debug!("ignoring peer {} as we are finished", addr);
                 state.peers.mark_peer_not_needed(addr);
                 continue;
@@ -583,6 +585,16 @@ impl TorrentStateLive {
                 continue;
             }
 
+            let family_allowed = state.shared.session.upgrade().map_or_else(
+                || true,
+                |session| session.peer_ip_family_allowed(outgoing_ip),
+            );
+            if !family_allowed {
+                debug!("Outgoing ip {outgoing_ip} is IPv6 and IPv6 peers are disabled, skipping");
+                state.peers.mark_peer_not_needed(addr);
+                continue;
+            }
+
             let permit = state.peer_semaphore.clone().acquire_owned().await?;
             state.spawn(
                 error_span!(parent: state.shared.span.clone(), "manage_peer", peer = addr.to_string()),
@@ -591,6 +603,21 @@ impl TorrentStateLive {
         }
     }
 
+    fn uploads_blocked(&self) -> bool {
+        self.shared
+            .session
+            .upgrade()
+            .is_some_and(|session| session.uploads_blocked())
+    }
+
+    fn pex_enabled(&self) -> bool {
+        self.shared
+            .session
+            .upgrade()
+            .is_some_and(|session| session.pex_enabled())
+            && !self.shared.isolation().private
+    }
+
     pub fn torrent(&self) -> &ManagedTorrentShared {
         &self.shared
     }
@@ -630,6 +657,17 @@ impl TorrentStateLive {
         self.peers.with_peer_mut(handle, "set_peer_live", |p| {
             p.connecting_to_live(Id20::new(h.peer_id), &self.peers);
         });
+        if self.uploads_blocked() {
+            self.peers.set_peer_choking(handle, true);
+        }
+    }
+
+    /// Choke every live peer while uploads are blocked, and unchoke them after.
+    pub(crate) fn set_uploads_blocked(&self, blocked: bool) {
+        let handles = self.peers.states.iter().map(|e| *e.key()).collect::<Vec<_>>();
+        for handle in handles {
+            self.peers.set_peer_choking(handle, blocked);
+        }
     }
 
     pub fn get_uploaded_bytes(&self) -> u64 {
@@ -890,7 +928,7 @@ impl TorrentStateLive {
             // it's assured by mutual exclusion of two  above sets  if in sent_peers_live, it cannot be in addrs_live_to_sent,
             // and addrs_closed_to_sent are only filtered addresses from sent_peers_live
 
-            if !connected.is_empty() || !dropped.is_empty() {
+            if (!connected.is_empty() || !dropped.is_empty()) && self.pex_enabled() {
                 let pex_msg = extended::ut_pex::UtPex::from_addrs(&connected, &dropped);
                 let ext_msg = extended::ExtendedMessage::UtPex(pex_msg);
                 if tx
@@ -1008,6 +1046,8 @@ impl PeerConnectionHandler for &PeerHandler {
                         "recieved noncompliant PEX message from {}, ignoring",
                         self.addr
                     );
+                } else if !self.state.pex_enabled() {
+                    trace!("PEX is disabled, ignoring PEX message from {}", self.addr);
                 } else {
                     self.on_pex_message(pex);
                 }
@@ -1323,6 +1363,15 @@ impl PeerHandler {
         if self.state.torrent().options.disable_upload() {
             anyhow::bail!("upload disabled, but peer requested a piece")
         }
+        // Seeding is stopped for now: the peer stays connected for downloading but is choked,
+        // and requests from a choked peer are discarded as BEP 3 allows.
+        if self.state.uploads_blocked() {
+            self.state.peers.set_peer_choking(self.addr, true);
+        }
+        if self.state.peers.is_peer_choked(self.addr) {
+            trace!("peer is choked, ignoring request {:?}", request);
+            return Ok(());
+        }
 
         let piece_index = match self.state.lengths.validate_piece_index(request.index) {
             Some(p) => p,
@@ -1860,6 +1909,10 @@ impl PeerHandler {
         B: AsRef<[u8]> + std::fmt::Debug,
     {
         // TODO: this is just first attempt at pex - will need more sophistication on adding peers - BEP 40,  check number of live, seen peers ...
+        self.state
+            .shared
+            .peer_sources
+            .add_pex_peers(msg.added_peers().count() as u64);
         msg.dropped_peers()
             .chain(msg.added_peers())
             .for_each(|peer| {
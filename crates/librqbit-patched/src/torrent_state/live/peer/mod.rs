@@ -241,6 +241,9 @@ pub(crate) struct LivePeerState {
 
     pub peer_interested: bool,
 
+    // Whether we choke the peer. Peers are unchoked when they connect.
+    pub am_choking: bool,
+
     // This is used to track the pieces the peer has.
     pub bitfield: BF,
 
@@ -256,6 +259,7 @@ impl LivePeerState {
         LivePeerState {
             peer_id,
             peer_interested: initial_interested,
+            am_choking: false,
             bitfield: BF::default(),
             inflight_requests: Default::default(),
             tx,
@@ -109,6 +109,22 @@ impl PeerStates {
         })
     }
 
+    /// Choke or unchoke a live peer, telling it only when that changes.
+    pub fn set_peer_choking(&self, handle: PeerHandle, choke: bool) {
+        self.with_live_mut(handle, "set_peer_choking", |live| {
+            if live.am_choking != choke {
+                live.am_choking = choke;
+                let msg = if choke { Message::Choke } else { Message::Unchoke };
+                let _ = live.tx.send(WriterRequest::Message(msg));
+            }
+        });
+    }
+
+    pub fn is_peer_choked(&self, handle: PeerHandle) -> bool {
+        self.with_live(handle, |live| live.am_choking)
+            .unwrap_or(false)
+    }
+
     pub fn update_bitfield(&self, handle: PeerHandle, bitfield: BF) -> Option<()> {
         self.with_live_mut(handle, "update_bitfield", |live| {
             live.bitfield = bitfield;
@@ -8,6 +8,7 @@ pub mod utils;
 use std::collections::HashSet;
 use std::net::SocketAddr;
 use std::path::PathBuf;
+use std::sync::atomic::AtomicU64;
 use std::sync::atomic::Ordering;
 use std::sync::Arc;
 use std::sync::Weak;
@@ -36,11 +37,13 @@ use tracing::debug;
 use tracing::error_span;
 use tracing::trace;
 use tracing::warn;
+use tracker_comms::TrackerStates;
 
 use crate::chunk_tracker::ChunkTracker;
 use crate::file_info::FileInfo;
 use crate::limits::LimitsConfig;
 use crate::session::TorrentId;
+use crate::session::TorrentIsolation;
 use crate::spawn_utils::BlockingSpawner;
 use crate::storage::BoxStorageFactory;
 use crate::stream_connect::StreamConnector;
@@ -106,6 +109,8 @@ pub(crate) struct ManagedTorrentLocked {
     pub(crate) paused: bool,
     pub(crate) state: ManagedTorrentState,
     pub(crate) only_files: Option<Vec<usize>>,
+    // Set while Session::relocate moves the files; the torrent can't be started or deleted.
+    pub(crate) relocating: bool,
 }
 
 #[derive(Default)]
@@ -114,10 +119,13 @@ pub(crate) struct ManagedTorrentOptions {
     pub peer_connect_timeout: Option<Duration>,
     pub peer_read_write_timeout: Option<Duration>,
     pub allow_overwrite: bool,
-    pub output_folder: PathBuf,
+    /// Changes only when the torrent is relocated (see [`Session::relocate`]).
+    pub output_folder: RwLock<PathBuf>,
     pub disk_write_queue: Option<DiskWorkQueueSender>,
     pub ratelimits: LimitsConfig,
     pub initial_peers: Vec<SocketAddr>,
+    /// Changes through [`Session::set_torrent_isolation`].
+    pub isolation: RwLock<TorrentIsolation>,
     #[cfg(feature = "disable-upload")]
     pub _disable_upload: bool,
 }
@@ -179,6 +187,37 @@ impl TorrentMetadata {
     }
 }
 
+/// Where the peers of a torrent come from: the state of its tracker announces and how many
+/// peers DHT and PEX have handed out. Lives as long as the torrent, across pauses.
+#[derive(Default)]
+pub struct PeerSources {
+    pub(crate) trackers: TrackerStates,
+    dht_peers: AtomicU64,
+    pex_peers: AtomicU64,
+}
+
+impl PeerSources {
+    pub fn trackers(&self) -> &TrackerStates {
+        &self.trackers
+    }
+
+    pub fn dht_peers(&self) -> u64 {
+        self.dht_peers.load(Ordering::Relaxed)
+    }
+
+    pub fn pex_peers(&self) -> u64 {
+        self.pex_peers.load(Ordering::Relaxed)
+    }
+
+    pub(crate) fn add_dht_peer(&self) {
+        self.dht_peers.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn add_pex_peers(&self, count: u64) {
+        self.pex_peers.fetch_add(count, Ordering::Relaxed);
+    }
+}
+
 /// Common information about torrent shared among all possible states.
 ///
 // The reason it's not inlined into ManagedTorrent is to break the Arc cycle:
@@ -188,18 +227,55 @@ pub struct ManagedTorrentShared {
     pub id: TorrentId,
     pub info_hash: Id20,
     pub(crate) spawner: BlockingSpawner,
-    pub trackers: HashSet<url::Url>,
+    // The torrent's own trackers by BEP 12 tier, see [`Session::set_torrent_trackers`].
+    pub(crate) trackers: RwLock<Vec<Vec<url::Url>>>,
     pub peer_id: Id20,
     pub span: tracing::Span,
     pub(crate) options: ManagedTorrentOptions,
     pub(crate) connector: Arc<StreamConnector>,
+    // Used instead of `connector` while the isolation asks for proxied peers.
+    pub(crate) proxy_connector: Arc<StreamConnector>,
     pub(crate) storage_factory: BoxStorageFactory,
     pub(crate) session: Weak<Session>,
+    pub(crate) peer_sources: Arc<PeerSources>,
 
     // "dn" from magnet link
     pub(crate) magnet_name: Option<String>,
 }
 
+impl ManagedTorrentShared {
+    pub fn output_folder(&self) -> PathBuf {
+        self.options.output_folder.read().clone()
+    }
+
+    pub fn isolation(&self) -> TorrentIsolation {
+        *self.options.isolation.read()
+    }
+
+    /// The torrent's own trackers by tier, without the session-wide ones.
+    pub fn trackers(&self) -> Vec<Vec<url::Url>> {
+        self.trackers.read().clone()
+    }
+
+    /// [`Self::trackers`] as one list, in tier order.
+    pub fn tracker_urls(&self) -> Vec<url::Url> {
+        self.trackers.read().iter().flatten().cloned().collect()
+    }
+
+    pub fn peer_sources(&self) -> &PeerSources {
+        &self.peer_sources
+    }
+
+    /// Connector for new peer connections under the current isolation.
+    pub(crate) fn peer_connector(&self) -> Arc<StreamConnector> {
+        if self.isolation().proxy_peers {
+            self.proxy_connector.clone()
+        } else {
+            self.connector.clone()
+        }
+    }
+}
+
 pub struct ManagedTorrent {
     // Static torrent configuration that doesn't change.
     pub shared: Arc<ManagedTorrentShared>,
@@ -274,6 +350,19 @@ impl ManagedTorrent {
         }
     }
 
+    /// Feed the peers of `peer_rx` to the torrent if it is live, as with the stream it was
+    /// started with. Returns `false` if it isn't live.
+    pub(crate) fn add_peer_stream(&self, peer_rx: PeerStream) -> bool {
+        let g = self.locked.read();
+        match &g.state {
+            ManagedTorrentState::Live(live) => {
+                spawn_peer_adder(live, peer_rx);
+                true
+            }
+            _ => false,
+        }
+    }
+
     fn stop_with_error(&self, error: anyhow::Error) {
         let mut g = self.locked.write();
 
@@ -405,6 +494,9 @@ impl ManagedTorrent {
             .upgrade()
             .context("session is dead, cannot start torrent")?;
         let mut g = self.locked.write();
+        if g.relocating {
+            bail!("torrent is being moved");
+        }
         g.paused = start_paused;
         let cancellation_token = session.cancellation_token().child_token();
 
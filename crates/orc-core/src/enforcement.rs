@@ -0,0 +1,182 @@
+//! Maps the effective policy onto the rqbit session.
+//!
+//! [`plan`] decides, per policy field, what the engine should do and whether it can do it at
+//! all; [`apply`] pushes the result into the session. Fields the engine cannot honor are
+//! reported through [`PolicyState::disabled`](crate::PolicyState) with a reason rather than
+//! being recorded as if they took effect.
+
+use std::collections::HashMap;
+
+use librqbit::Session;
+
+use crate::{EffectivePolicy, ToggleDisabled, TriState};
+
+const NO_OVERLAY: &str = "The anonymity overlay is not available in this build";
+
+/// What the running session was started with, which bounds what the policy can change.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct EngineCaps {
+    /// `upnp_port_forwarding` in the daemon settings.
+    pub upnp_configured: bool,
+    pub dht_running: bool,
+    /// `bind_interface` in the daemon settings.
+    pub bound: bool,
+}
+
+#[derive(Debug, Clone)]
+pub(crate) struct Enforcement {
+    pub upnp_port_forwarding: bool,
+    pub dht_peer_discovery: bool,
+    pub ipv6_peers: bool,
+    pub disabled: HashMap<String, ToggleDisabled>,
+}
+
+pub(crate) fn plan(effective: &EffectivePolicy, caps: EngineCaps) -> Enforcement {
+    let mut disabled = HashMap::new();
+    let mut mark = |key: &str, reason: Option<&str>| {
+        disabled.insert(
+            key.to_string(),
+            ToggleDisabled {
+                disabled: reason.is_some(),
+                reason: reason.map(str::to_string),
+            },
+        );
+    };
+
+    // Enforced by the engine for each torrent carrying the BEP 27 flag.
+    mark("enforce_private_torrents", None);
+    // Enforced here.
+    mark("ipv6_enabled", None);
+    mark(
+        "upnp_natpmp_enabled",
+        if !caps.upnp_configured {
+            Some("UPnP port forwarding is turned off in the daemon settings (upnp_port_forwarding)")
+        } else if caps.bound {
+            // The router's mapping would point at a listener that only accepts from the binding.
+            Some("UPnP port forwarding is off while bind_interface is set")
+        } else {
+            None
+        },
+    );
+    // Enforced elsewhere: the kill switch by its own controller, the profile is a preset label.
+    mark("kill_switch", None);
+    mark("profile", None);
+
+    // Not supported by the engine.
+    mark(
+        "peer_encryption",
+        (effective.peer_encryption != TriState::Off)
+            .then_some("The engine does not support peer encryption (MSE/PE); connections are unencrypted"),
+    );
+    mark("dht_hardening", Some("The engine's DHT has no hardening options"));
+    mark("ip_blocklist", Some("No IP blocklist source is configured"));
+    // Follows the `bind_interface` setting rather than the toggle; see `effective_policy`.
+    mark(
+        "bind_interface_only",
+        Some(if caps.bound {
+            "Torrent traffic is bound by bind_interface in the daemon settings"
+        } else {
+            "Set bind_interface in the daemon settings to bind torrent traffic to one interface"
+        }),
+    );
+    mark("minimize_fingerprinting", Some("The engine does not support fingerprint minimization"));
+    for key in [
+        "anonymous_mode",
+        "overlay_padding",
+        "sybil_resistance",
+        "relay_pow_required",
+        "relay_subnet_diversity",
+        "relay_reputation_weighting",
+        "circuit_rotation_enabled",
+        "deny_direct_exits",
+    ] {
+        mark(key, Some(NO_OVERLAY));
+    }
+
+    Enforcement {
+        upnp_port_forwarding: caps.upnp_configured && !caps.bound && effective.upnp_natpmp_enabled,
+        dht_peer_discovery: caps.dht_running && effective.discovery_allowed,
+        ipv6_peers: effective.ipv6_enabled,
+        disabled,
+    }
+}
+
+pub(crate) fn apply(session: &Session, enforcement: &Enforcement) {
+    session.set_upnp_port_forwarding(enforcement.upnp_port_forwarding);
+    session.set_dht_peer_discovery(enforcement.dht_peer_discovery);
+    session.set_ipv6_peers(enforcement.ipv6_peers);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{effective_policy, DesiredPolicy};
+
+    const CAPS: EngineCaps = EngineCaps { upnp_configured: true, dht_running: true, bound: false };
+
+    fn effective(desired: &DesiredPolicy) -> EffectivePolicy {
+        effective_policy(desired, true, CAPS).0
+    }
+
+    #[test]
+    fn every_policy_field_has_a_status() {
+        let enforcement = plan(&effective(&DesiredPolicy::default()), CAPS);
+        let desired = serde_json::to_value(DesiredPolicy::default()).unwrap();
+        let mut keys: Vec<_> = desired.as_object().unwrap().keys().cloned().collect();
+        let mut reported: Vec<_> = enforcement.disabled.keys().cloned().collect();
+        keys.sort();
+        reported.sort();
+        assert_eq!(keys, reported);
+    }
+
+    #[test]
+    fn engine_switches_follow_policy() {
+        let defaults = plan(&effective(&DesiredPolicy::default()), CAPS);
+        assert!(defaults.upnp_port_forwarding);
+        assert!(defaults.dht_peer_discovery);
+        assert!(defaults.ipv6_peers);
+
+        let strict = DesiredPolicy {
+            enforce_private_torrents: true,
+            ipv6_enabled: false,
+            upnp_natpmp_enabled: false,
+            ..Default::default()
+        };
+        let strict = plan(&effective(&strict), CAPS);
+        assert!(!strict.upnp_port_forwarding);
+        assert!(strict.dht_peer_discovery);
+        assert!(!strict.ipv6_peers);
+    }
+
+    #[test]
+    fn unsupported_fields_are_disabled_with_reason() {
+        let enforcement = plan(
+            &effective(&DesiredPolicy::default()),
+            EngineCaps { upnp_configured: false, dht_running: true, bound: false },
+        );
+        assert!(!enforcement.upnp_port_forwarding);
+        for key in ["upnp_natpmp_enabled", "peer_encryption", "overlay_padding", "ip_blocklist"] {
+            let status = &enforcement.disabled[key];
+            assert!(status.disabled, "{key}");
+            assert!(status.reason.is_some(), "{key}");
+        }
+        assert!(!enforcement.disabled["ipv6_enabled"].disabled);
+
+        let plaintext = DesiredPolicy { peer_encryption: TriState::Off, ..Default::default() };
+        assert!(!plan(&effective(&plaintext), CAPS).disabled["peer_encryption"].disabled);
+    }
+
+    #[test]
+    fn binding_follows_the_daemon_setting() {
+        let desired = DesiredPolicy { bind_interface_only: true, ..Default::default() };
+        assert!(!effective(&desired).bind_interface_only);
+        assert!(plan(&effective(&desired), CAPS).disabled["bind_interface_only"].disabled);
+
+        let bound = EngineCaps { bound: true, ..CAPS };
+        let effective = effective_policy(&DesiredPolicy::default(), true, bound).0;
+        assert!(effective.bind_interface_only);
+        assert!(!plan(&effective, bound).upnp_port_forwarding);
+        let status = &plan(&effective, bound).disabled["bind_interface_only"];
+        assert!(status.reason.as_deref().unwrap().contains("bound"));
+    }
+}
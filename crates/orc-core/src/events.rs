@@ -0,0 +1,283 @@
+//! Change events for `GET /v1/events`.
+//!
+//! [`tick`](crate::tick) diffs the state against what was last published and pushes typed
+//! events through a broadcast channel. The most recent events are kept so a reconnecting
+//! client can resume from its last event id; if that id has already been dropped it gets a
+//! [`OrcEvent::Resync`] and should reload full state.
+//!
+//! Progress is coalesced into one [`OrcEvent::TorrentProgress`] per tick carrying only the
+//! fields that changed, so the history spans minutes however many torrents are active.
+//!
+//! The stream needs the bearer token like every other route. A browser `EventSource` cannot
+//! send headers, so clients read it with `fetch`.
+
+use std::collections::{HashMap, VecDeque};
+use std::sync::Arc;
+
+use serde::Serialize;
+use serde_json::{Map, Value};
+use tokio::sync::broadcast;
+
+use crate::{now_ms, KillSwitchState, TorrentState, TorrentStatus};
+
+/// Events kept for resume.
+const HISTORY_LEN: usize = 1024;
+const CHANNEL_CAPACITY: usize = 256;
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum OrcEvent {
+    TorrentAdded { id: String, name: String },
+    TorrentRemoved { id: String },
+    TorrentStateChanged { id: String, from: TorrentState, to: TorrentState },
+    /// Once per tick for every torrent whose status changed: its `id` and the fields that
+    /// changed, a removed optional field as `null`. A torrent seen for the first time has
+    /// every field.
+    TorrentProgress { torrents: Vec<Map<String, Value>> },
+    TorrentError { id: String, message: String },
+    TorrentMoved { id: String, save_path: String },
+    KillSwitchEngaged,
+    KillSwitchReleased,
+    PolicyChanged { version: u64 },
+    /// Events were missed; the client should refetch everything.
+    Resync,
+}
+
+impl OrcEvent {
+    /// Name used for the SSE `event:` field.
+    pub fn name(&self) -> &'static str {
+        match self {
+            OrcEvent::TorrentAdded { .. } => "torrent_added",
+            OrcEvent::TorrentRemoved { .. } => "torrent_removed",
+            OrcEvent::TorrentStateChanged { .. } => "torrent_state_changed",
+            OrcEvent::TorrentProgress { .. } => "torrent_progress",
+            OrcEvent::TorrentError { .. } => "torrent_error",
+            OrcEvent::TorrentMoved { .. } => "torrent_moved",
+            OrcEvent::KillSwitchEngaged => "kill_switch_engaged",
+            OrcEvent::KillSwitchReleased => "kill_switch_released",
+            OrcEvent::PolicyChanged { .. } => "policy_changed",
+            OrcEvent::Resync => "resync",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct EventEnvelope {
+    /// Monotonic per daemon run; used as the SSE event id.
+    pub seq: u64,
+    pub ts_ms: u64,
+    #[serde(flatten)]
+    pub event: OrcEvent,
+}
+
+pub type EventReceiver = broadcast::Receiver<Arc<EventEnvelope>>;
+
+pub(crate) struct EventBus {
+    tx: broadcast::Sender<Arc<EventEnvelope>>,
+    next_seq: u64,
+    history: VecDeque<Arc<EventEnvelope>>,
+    published: Published,
+}
+
+/// What subscribers were last told, so `tick` only publishes differences.
+#[derive(Default)]
+pub(crate) struct Published {
+    pub torrents: HashMap<String, TorrentStatus>,
+    pub kill_switch_engaged: bool,
+    pub policy_version: u64,
+}
+
+impl EventBus {
+    pub fn new(policy_version: u64) -> Self {
+        let (tx, _) = broadcast::channel(CHANNEL_CAPACITY);
+        Self {
+            tx,
+            next_seq: 1,
+            history: VecDeque::with_capacity(HISTORY_LEN),
+            published: Published { policy_version, ..Default::default() },
+        }
+    }
+
+    pub fn publish(&mut self, event: OrcEvent) {
+        let envelope = Arc::new(EventEnvelope { seq: self.next_seq, ts_ms: now_ms(), event });
+        self.next_seq += 1;
+        if self.history.len() == HISTORY_LEN {
+            self.history.pop_front();
+        }
+        self.history.push_back(envelope.clone());
+        // No receivers is fine: nobody is listening.
+        let _ = self.tx.send(envelope);
+    }
+
+    /// Subscribe, replaying everything after `last_seq`. Called with the state lock held, so
+    /// no event can fall between the replay and the live receiver.
+    pub fn subscribe(&self, last_seq: Option<u64>) -> (Vec<Arc<EventEnvelope>>, EventReceiver) {
+        let rx = self.tx.subscribe();
+        let Some(last) = last_seq else {
+            return (Vec::new(), rx);
+        };
+        let oldest = self.history.front().map(|e| e.seq).unwrap_or(self.next_seq);
+        // Ids from another daemon run, or older than what we kept, cannot be resumed.
+        if last >= self.next_seq || last + 1 < oldest {
+            let resync = Arc::new(EventEnvelope {
+                seq: self.next_seq.saturating_sub(1),
+                ts_ms: now_ms(),
+                event: OrcEvent::Resync,
+            });
+            return (vec![resync], rx);
+        }
+        let replay = self.history.iter().filter(|e| e.seq > last).cloned().collect();
+        (replay, rx)
+    }
+
+    pub fn published(&mut self) -> &mut Published {
+        &mut self.published
+    }
+}
+
+/// Events for one torrent given what was last published for it, and its entry for the tick's
+/// [`OrcEvent::TorrentProgress`] if anything changed.
+pub(crate) fn torrent_events(
+    prev: Option<&TorrentStatus>,
+    status: &TorrentStatus,
+) -> (Vec<OrcEvent>, Option<Map<String, Value>>) {
+    let mut events = Vec::new();
+    let Some(prev) = prev else {
+        return (events, Some(status_fields(status)));
+    };
+    if prev == status {
+        return (events, None);
+    }
+    if prev.state != status.state {
+        events.push(OrcEvent::TorrentStateChanged {
+            id: status.id.clone(),
+            from: prev.state.clone(),
+            to: status.state.clone(),
+        });
+    }
+    if let Some(message) = &status.error {
+        if prev.error.as_ref() != Some(message) {
+            events.push(OrcEvent::TorrentError { id: status.id.clone(), message: message.clone() });
+        }
+    }
+    (events, Some(changed_fields(prev, status)))
+}
+
+fn status_fields(status: &TorrentStatus) -> Map<String, Value> {
+    match serde_json::to_value(status) {
+        Ok(Value::Object(map)) => map,
+        _ => Map::new(),
+    }
+}
+
+// `id` and every field of `status` that differs from `prev`.
+fn changed_fields(prev: &TorrentStatus, status: &TorrentStatus) -> Map<String, Value> {
+    let prev = status_fields(prev);
+    let next = status_fields(status);
+    let mut out = Map::new();
+    out.insert("id".to_string(), Value::from(status.id.clone()));
+    for key in prev.keys() {
+        if !next.contains_key(key) {
+            out.insert(key.clone(), Value::Null);
+        }
+    }
+    for (key, value) in next {
+        if prev.get(&key) != Some(&value) {
+            out.insert(key, value);
+        }
+    }
+    out
+}
+
+/// `Releasing` keeps torrents paused until the grace period ends, so it still counts.
+pub(crate) fn kill_switch_engaged(state: &KillSwitchState) -> bool {
+    matches!(state, KillSwitchState::Engaged | KillSwitchState::Releasing)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn status(state: TorrentState, downloaded: u64) -> TorrentStatus {
+        TorrentStatus {
+            id: "t".to_string(),
+            state,
+            progress: 0.0,
+            down_rate_bps: 0,
+            up_rate_bps: 0,
+            eta_sec: 0,
+            total_bytes: 100,
+            downloaded_bytes: downloaded,
+            peers_seen: 0,
+            error: None,
+            moving: None,
+            swarm: None,
+        }
+    }
+
+    #[test]
+    fn resume_replays_missed_events() {
+        let mut bus = EventBus::new(1);
+        for v in 2..=4 {
+            bus.publish(OrcEvent::PolicyChanged { version: v });
+        }
+        let (replay, _rx) = bus.subscribe(Some(1));
+        assert_eq!(replay.iter().map(|e| e.seq).collect::<Vec<_>>(), vec![2, 3]);
+        let (replay, _rx) = bus.subscribe(Some(3));
+        assert!(replay.is_empty());
+        let (replay, _rx) = bus.subscribe(None);
+        assert!(replay.is_empty());
+    }
+
+    #[test]
+    fn unknown_or_expired_ids_resync() {
+        let mut bus = EventBus::new(1);
+        for _ in 0..HISTORY_LEN + 10 {
+            bus.publish(OrcEvent::KillSwitchEngaged);
+        }
+        let (replay, _rx) = bus.subscribe(Some(1));
+        assert!(matches!(replay[0].event, OrcEvent::Resync));
+        let (replay, _rx) = bus.subscribe(Some(1_000_000));
+        assert!(matches!(replay[0].event, OrcEvent::Resync));
+    }
+
+    #[test]
+    fn live_subscribers_receive_published_events() {
+        let mut bus = EventBus::new(1);
+        let (_, mut rx) = bus.subscribe(None);
+        bus.publish(OrcEvent::KillSwitchReleased);
+        let got = rx.try_recv().expect("event");
+        assert_eq!(got.event.name(), "kill_switch_released");
+    }
+
+    #[test]
+    fn torrent_diff_reports_state_change_and_changed_fields() {
+        let prev = status(TorrentState::Downloading, 10);
+        let (events, fields) = torrent_events(Some(&prev), &prev);
+        assert!(events.is_empty() && fields.is_none());
+
+        let (events, fields) = torrent_events(None, &prev);
+        assert!(events.is_empty());
+        assert_eq!(fields.unwrap()["total_bytes"], 100);
+
+        let mut next = status(TorrentState::Downloading, 60);
+        next.down_rate_bps = 5;
+        let (events, fields) = torrent_events(Some(&prev), &next);
+        assert!(events.is_empty());
+        assert_eq!(
+            Value::Object(fields.unwrap()),
+            serde_json::json!({"id": "t", "downloaded_bytes": 60, "down_rate_bps": 5})
+        );
+
+        let mut failed = status(TorrentState::Error, 10);
+        failed.error = Some("disk full".to_string());
+        let (events, fields) = torrent_events(Some(&prev), &failed);
+        let names: Vec<_> = events.iter().map(|e| e.name()).collect();
+        assert_eq!(names, vec!["torrent_state_changed", "torrent_error"]);
+        assert_eq!(fields.as_ref().unwrap()["error"], "disk full");
+
+        // A field that went away is reported as null.
+        let (_, fields) = torrent_events(Some(&failed), &prev);
+        assert_eq!(fields.unwrap()["error"], Value::Null);
+    }
+}
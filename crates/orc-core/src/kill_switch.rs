@@ -0,0 +1,706 @@
+//! Kill switch state machine and its enforcement on the rqbit session.
+//!
+//! [`advance`] moves [`KillSwitchConfig::enforcement_state`](crate::KillSwitchConfig) from the
+//! latest [`VpnProbe`] reading for the configured `vpn_source`: `Armed` goes to `Engaged` as soon as the VPN drops, `Engaged` to
+//! `Releasing` when it comes back, and `Releasing` to `Armed` once it has stayed up for
+//! `grace_period_sec`. A drop during `Releasing` goes straight back to `Engaged`, so a flapping
+//! link keeps the kill switch engaged until it settles.
+//!
+//! While engaged, [`actions`] picks what to cut from the scope and triggers and
+//! [`enforce_kill_switch`] makes the session match, undoing only what it did itself:
+//! - the peer listener is always closed and trackers are no longer scraped;
+//! - `pause_all_torrents` pauses every live torrent, including ones that go live later;
+//! - `stop_seeding` stops serving pieces while downloads carry on;
+//! - `disable_dht_pex_lpd` closes the DHT socket and stops PEX (the engine has no LSD);
+//! - `block_outbound` refuses new outbound peer and tracker connections.
+//!
+//! [`KillSwitchScope::AppLevel`] applies all of them regardless of the triggers. Only the
+//! torrents paused here are resumed on release, so a torrent the user stopped stays stopped.
+//!
+//! [`test_kill_switch`] runs the same path against a simulated VPN drop and reports what it
+//! cut off and whether anything still got through.
+
+use std::collections::HashMap;
+use std::net::{SocketAddr, TcpListener, UdpSocket};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use anyhow::{anyhow, Result};
+use librqbit::api::TorrentIdOrHash;
+use librqbit::ManagedTorrentState;
+use serde::Serialize;
+use tracing::{info, warn};
+
+use crate::vpn::{self, DnsServer, Route, VpnProbe};
+use crate::{
+    now_ms, KillSwitchConfig, KillSwitchScope, KillSwitchSettings, KillSwitchState, OrcState, SharedState,
+};
+
+/// How long the dry run watches for traffic after the kill switch engaged.
+const TEST_WINDOW: Duration = Duration::from_millis(1500);
+
+/// Move the kill switch state for the current probe reading. Returns the new state if it
+/// changed.
+pub(crate) fn advance(
+    config: &mut KillSwitchConfig,
+    probe: &dyn VpnProbe,
+    now_ms: u64,
+) -> Option<KillSwitchState> {
+    if !config.enabled {
+        return None;
+    }
+    let connected = vpn::connected(&vpn::status(&config.vpn_source, probe, now_ms));
+    let since_ms = now_ms.saturating_sub(config.last_enforcement_ms.unwrap_or(0));
+    let next = match (&config.enforcement_state, connected) {
+        (KillSwitchState::Armed | KillSwitchState::Disarmed, false) => KillSwitchState::Engaged,
+        (KillSwitchState::Disarmed, true) => KillSwitchState::Armed,
+        (KillSwitchState::Engaged, true) => KillSwitchState::Releasing,
+        (KillSwitchState::Releasing, false) => KillSwitchState::Engaged,
+        // Resume only once the VPN has stayed up for the whole grace period.
+        (KillSwitchState::Releasing, true)
+            if since_ms >= config.grace_period_sec.saturating_mul(1000) =>
+        {
+            KillSwitchState::Armed
+        }
+        _ => return None,
+    };
+    config.enforcement_state = next.clone();
+    config.last_enforcement_ms = Some(now_ms);
+    Some(next)
+}
+
+/// Whether the kill switch is holding traffic back. `Releasing` still holds: the VPN is back
+/// but has not been up for the grace period yet.
+pub(crate) fn engaged(config: &KillSwitchConfig) -> bool {
+    config.enabled
+        && matches!(
+            config.enforcement_state,
+            KillSwitchState::Engaged | KillSwitchState::Releasing
+        )
+}
+
+/// Whether an engaged kill switch takes torrents off the network, rather than only limiting
+/// what they do.
+pub(crate) fn pauses_torrents(config: &KillSwitchConfig) -> bool {
+    matches!(config.scope, KillSwitchScope::AppLevel) || config.triggers.pause_all_torrents
+}
+
+/// What the kill switch does to the session.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub(crate) struct KillSwitchActions {
+    pub close_listener: bool,
+    pub stop_scraping: bool,
+    pub pause_torrents: bool,
+    pub stop_seeding: bool,
+    pub stop_discovery: bool,
+    pub block_outbound: bool,
+}
+
+pub(crate) fn actions(config: &KillSwitchConfig) -> KillSwitchActions {
+    if !engaged(config) {
+        return KillSwitchActions::default();
+    }
+    match config.scope {
+        KillSwitchScope::AppLevel => KillSwitchActions {
+            close_listener: true,
+            stop_scraping: true,
+            pause_torrents: true,
+            stop_seeding: true,
+            stop_discovery: true,
+            block_outbound: true,
+        },
+        KillSwitchScope::TorrentOnly => KillSwitchActions {
+            close_listener: true,
+            // Scrapes cover paused torrents, so pausing alone would not stop them.
+            stop_scraping: true,
+            pause_torrents: config.triggers.pause_all_torrents,
+            stop_seeding: config.triggers.stop_seeding,
+            stop_discovery: config.triggers.disable_dht_pex_lpd,
+            block_outbound: config.triggers.block_outbound,
+        },
+    }
+}
+
+/// What the kill switch has applied to the session, so each change is made and undone once.
+#[derive(Debug, Default)]
+pub(crate) struct KillSwitchEnforcement {
+    pub applied: KillSwitchActions,
+    /// orc ids of the torrents the kill switch paused.
+    pub paused: Vec<String>,
+    /// A dry run has simulated a VPN drop and will undo it.
+    pub testing: bool,
+    /// `enabled` as it was before the dry run forced the kill switch on.
+    pub enabled_before_test: bool,
+}
+
+/// Make the session match the kill switch state. Called by the daemon after every
+/// [`tick`](crate::tick) and after kill switch settings change.
+pub async fn enforce_kill_switch(state: &mut OrcState) {
+    let want = actions(&state.kill_switch);
+    let have = state.kill_switch_enforcement.applied;
+    let session = state.rqbit.session().clone();
+
+    if want.close_listener != have.close_listener {
+        if want.close_listener {
+            session.close_listener();
+        } else if let Err(e) = session.reopen_listener().await {
+            warn!("Kill switch could not reopen the peer listener: {e:#}");
+        }
+    }
+    if want.stop_scraping != have.stop_scraping {
+        session.set_scraping_blocked(want.stop_scraping);
+    }
+    if want.stop_discovery != have.stop_discovery {
+        if let Some(dht) = session.get_dht() {
+            if want.stop_discovery {
+                dht.suspend();
+            } else if let Err(e) = dht.resume().await {
+                warn!("Kill switch could not reopen the DHT socket: {e:#}");
+            }
+        }
+        session.set_pex(!want.stop_discovery);
+    }
+    if want.stop_seeding != have.stop_seeding {
+        session.set_uploads_blocked(want.stop_seeding);
+    }
+    if want.block_outbound != have.block_outbound {
+        session.set_outbound_blocked(want.block_outbound);
+    }
+    if want != have {
+        info!("Kill switch actions: {:?}", want);
+        state.kill_switch_enforcement.applied = want;
+    }
+
+    if want.pause_torrents {
+        pause_live_torrents(state);
+    } else if !state.kill_switch_enforcement.paused.is_empty() {
+        resume_paused_torrents(state).await;
+    }
+}
+
+fn pause_live_torrents(state: &mut OrcState) {
+    let session = state.rqbit.session().clone();
+    for rec in state.torrents.values() {
+        let Some(handle) = session.get(TorrentIdOrHash::Id(rec.runtime.rqbit_id)) else {
+            continue;
+        };
+        if !handle.with_state(|s| matches!(s, ManagedTorrentState::Live(_))) {
+            continue;
+        }
+        match session.pause_transient(&handle) {
+            Ok(()) => {
+                info!("Kill switch paused torrent {}", rec.torrent.id);
+                state.kill_switch_enforcement.paused.push(rec.torrent.id.clone());
+            }
+            Err(e) => warn!("Kill switch could not pause torrent {}: {e:#}", rec.torrent.id),
+        }
+    }
+}
+
+async fn resume_paused_torrents(state: &mut OrcState) {
+    let paused = std::mem::take(&mut state.kill_switch_enforcement.paused);
+    let session = state.rqbit.session().clone();
+    let mut resumed = 0;
+    for id in &paused {
+        // Removed while the kill switch was engaged.
+        let Some(rec) = state.torrents.get(id) else {
+            continue;
+        };
+        let Some(handle) = session.get(TorrentIdOrHash::Id(rec.runtime.rqbit_id)) else {
+            continue;
+        };
+        match session.unpause(&handle).await {
+            Ok(()) => resumed += 1,
+            Err(e) => warn!("Kill switch could not resume torrent {}: {e:#}", id),
+        }
+    }
+    info!("Kill switch resumed {} torrents", resumed);
+}
+
+/// The kill switch settings to save. While a dry run holds the kill switch on, `enabled` is
+/// the value the run will put back rather than the one it forced.
+pub fn kill_switch_settings(state: &OrcState) -> KillSwitchSettings {
+    let mut settings = state.kill_switch.settings();
+    if state.kill_switch_enforcement.testing && state.kill_switch.enabled {
+        settings.enabled = state.kill_switch_enforcement.enabled_before_test;
+    }
+    settings
+}
+
+/// Whether the torrent is paused by the kill switch rather than by the user.
+pub fn held_by_kill_switch(state: &OrcState, id: &str) -> bool {
+    state.kill_switch_enforcement.paused.iter().any(|p| p == id)
+}
+
+/// The user stopped a torrent while the kill switch held it; keep it stopped on release.
+pub(crate) fn forget_paused(state: &mut OrcState, id: &str) {
+    state.kill_switch_enforcement.paused.retain(|p| p != id);
+}
+
+/// Probe used by [`test_kill_switch`] in place of the real one.
+struct SimulatedVpnDown;
+
+impl VpnProbe for SimulatedVpnDown {
+    fn interfaces(&self) -> Vec<String> {
+        Vec::new()
+    }
+
+    fn routes(&self) -> Vec<Route> {
+        Vec::new()
+    }
+
+    fn dns_servers(&self) -> Vec<DnsServer> {
+        Vec::new()
+    }
+}
+
+/// Result of `POST /net/kill-switch/test`.
+#[derive(Debug, Serialize)]
+pub struct KillSwitchTestReport {
+    /// Enforced with no leak, no socket left open, and the prior state restored.
+    pub ok: bool,
+    pub message: String,
+    /// A disabled kill switch is tested with its current scope and triggers.
+    pub enabled: bool,
+    /// From the simulated drop until the session matched the kill switch.
+    pub time_to_enforce_ms: f64,
+    /// orc ids of the torrents the kill switch paused.
+    pub paused: Vec<String>,
+    pub closed_connections: Vec<KillSwitchConnection>,
+    /// Sockets the kill switch should have closed that were still bound after the window.
+    pub open_sockets: Vec<KillSwitchConnection>,
+    /// Torrents that moved data, or kept peers while they should have been paused, during
+    /// the window.
+    pub leaks: Vec<TorrentLeak>,
+    pub restored: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum KillSwitchConnection {
+    PeerListener { port: u16 },
+    Dht { port: u16 },
+    Peers { torrent_id: String, count: usize },
+}
+
+#[derive(Debug, Serialize)]
+pub struct TorrentLeak {
+    pub torrent_id: String,
+    pub downloaded_bytes: u64,
+    pub uploaded_bytes: u64,
+    pub live_peers: usize,
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+struct TransferSample {
+    live: bool,
+    downloaded: u64,
+    uploaded: u64,
+    live_peers: usize,
+}
+
+fn sample_torrents(state: &OrcState) -> HashMap<String, TransferSample> {
+    state
+        .torrents
+        .values()
+        .filter_map(|rec| {
+            let stats = state.rqbit.api_stats_v1(TorrentIdOrHash::Id(rec.runtime.rqbit_id)).ok()?;
+            let sample = TransferSample {
+                live: stats.live.is_some(),
+                downloaded: stats.progress_bytes,
+                uploaded: stats.uploaded_bytes,
+                live_peers: stats.live.as_ref().map_or(0, |l| l.snapshot.peer_stats.live),
+            };
+            Some((rec.torrent.id.clone(), sample))
+        })
+        .collect()
+}
+
+/// Traffic between `start` and `end` that the applied actions should have stopped. A paused
+/// torrent has no live state at all, so one that still has it was never paused or came back.
+fn leaked(actions: &KillSwitchActions, start: &TransferSample, end: &TransferSample) -> bool {
+    let downloaded = end.downloaded > start.downloaded;
+    let uploaded = end.uploaded > start.uploaded;
+    if actions.pause_torrents {
+        end.live || downloaded || uploaded
+    } else {
+        actions.stop_seeding && uploaded
+    }
+}
+
+/// Whether a socket still holds `addr`, judged by trying to bind it ourselves.
+fn tcp_bound(addr: SocketAddr) -> bool {
+    TcpListener::bind(addr).is_err()
+}
+
+fn udp_bound(addr: SocketAddr) -> bool {
+    UdpSocket::bind(addr).is_err()
+}
+
+/// Dry run: feed the state machine a VPN drop, enforce it on the session, watch for traffic
+/// for a short window, then undo the drop. The state lock is released for the window, so
+/// `tick` and API calls carry on and see the kill switch engaged; a change made meanwhile,
+/// disabling it included, is kept.
+pub async fn test_kill_switch(shared: &SharedState) -> Result<KillSwitchTestReport> {
+    // Run to the end even if the caller goes away, so the simulated drop is always undone.
+    let shared = shared.clone();
+    tokio::spawn(async move { run_test(&shared).await })
+        .await
+        .map_err(|e| anyhow!("Kill switch test failed: {e}"))?
+}
+
+async fn run_test(shared: &SharedState) -> Result<KillSwitchTestReport> {
+    let mut state = shared.lock().await;
+    if state.kill_switch_enforcement.testing {
+        return Err(anyhow!("A kill switch test is already running"));
+    }
+    if state.kill_switch_enforcement.applied != KillSwitchActions::default() {
+        return Err(anyhow!("Kill switch is engaged; run the test once the VPN is back"));
+    }
+    let enabled = state.kill_switch.enabled;
+    let prior_state = state.kill_switch.enforcement_state.clone();
+    let prior_enforcement_ms = state.kill_switch.last_enforcement_ms;
+    let prior_probe = std::mem::replace(&mut state.vpn_probe, Arc::new(SimulatedVpnDown));
+    let session = state.rqbit.session().clone();
+    let listener = session.listen_info().tcp_port.zip(session.tcp_listen_addr());
+    let dht = session.get_dht().map(|d| d.listen_addr());
+    let before = sample_torrents(&state);
+
+    info!("Kill switch test: simulating a VPN drop");
+    state.kill_switch.enabled = true;
+    state.kill_switch_enforcement.testing = true;
+    state.kill_switch_enforcement.enabled_before_test = enabled;
+    let started = Instant::now();
+    let probe = state.vpn_probe.clone();
+    advance(&mut state.kill_switch, &*probe, now_ms());
+    enforce_kill_switch(&mut state).await;
+    let time_to_enforce = started.elapsed();
+    let applied = state.kill_switch_enforcement.applied;
+    let paused = state.kill_switch_enforcement.paused.clone();
+    let enforced = sample_torrents(&state);
+    drop(state);
+
+    tokio::time::sleep(TEST_WINDOW).await;
+
+    let mut state = shared.lock().await;
+    let after = sample_torrents(&state);
+    let mut closed_connections = Vec::new();
+    let mut open_sockets = Vec::new();
+    if let (true, Some((port, addr))) = (applied.close_listener, listener) {
+        closed_connections.push(KillSwitchConnection::PeerListener { port });
+        if tcp_bound(addr) {
+            open_sockets.push(KillSwitchConnection::PeerListener { port });
+        }
+    }
+    if let (true, Some(addr)) = (applied.stop_discovery, dht) {
+        closed_connections.push(KillSwitchConnection::Dht { port: addr.port() });
+        if udp_bound(addr) {
+            open_sockets.push(KillSwitchConnection::Dht { port: addr.port() });
+        }
+    }
+    let mut leaks = Vec::new();
+    for (id, start) in &before {
+        let (Some(enforced), Some(end)) = (enforced.get(id), after.get(id)) else {
+            continue;
+        };
+        let closed = start.live_peers.saturating_sub(end.live_peers);
+        if closed > 0 {
+            closed_connections.push(KillSwitchConnection::Peers { torrent_id: id.clone(), count: closed });
+        }
+        if start.live && leaked(&applied, enforced, end) {
+            leaks.push(TorrentLeak {
+                torrent_id: id.clone(),
+                downloaded_bytes: end.downloaded.saturating_sub(enforced.downloaded),
+                uploaded_bytes: end.uploaded.saturating_sub(enforced.uploaded),
+                live_peers: end.live_peers,
+            });
+        }
+    }
+
+    // Still held on as the run left it; otherwise it was disabled meanwhile and stays so.
+    if state.kill_switch.enabled {
+        state.kill_switch.enabled = enabled;
+        state.kill_switch.enforcement_state = prior_state;
+        state.kill_switch.last_enforcement_ms = prior_enforcement_ms;
+    }
+    state.vpn_probe = prior_probe;
+    state.kill_switch_enforcement.testing = false;
+    enforce_kill_switch(&mut state).await;
+    let restored = state.kill_switch_enforcement.applied == KillSwitchActions::default()
+        && state.kill_switch_enforcement.paused.is_empty();
+
+    let ok = leaks.is_empty() && open_sockets.is_empty() && restored;
+    let time_to_enforce_ms = time_to_enforce.as_secs_f64() * 1000.0;
+    let message = if ok {
+        format!(
+            "Kill switch engaged in {:.1} ms: paused {} torrents, closed {} connections, nothing leaked.",
+            time_to_enforce_ms,
+            paused.len(),
+            closed_connections.len()
+        )
+    } else if !restored {
+        "Kill switch test could not restore the previous state.".to_string()
+    } else {
+        format!(
+            "Kill switch leaked: {} torrents moved data and {} sockets stayed open.",
+            leaks.len(),
+            open_sockets.len()
+        )
+    };
+    info!("Kill switch test: {}", message);
+    Ok(KillSwitchTestReport {
+        ok,
+        message,
+        enabled,
+        time_to_enforce_ms,
+        paused,
+        closed_connections,
+        open_sockets,
+        leaks,
+        restored,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicBool, Ordering};
+
+    use super::*;
+    use crate::KillSwitchSettings;
+
+    #[derive(Default)]
+    struct FakeProbe(AtomicBool);
+
+    impl FakeProbe {
+        fn set(&self, connected: bool) {
+            self.0.store(connected, Ordering::Relaxed);
+        }
+    }
+
+    impl VpnProbe for FakeProbe {
+        fn interfaces(&self) -> Vec<String> {
+            let mut interfaces = vec!["eth0".to_string()];
+            if self.0.load(Ordering::Relaxed) {
+                interfaces.push("wg0".to_string());
+            }
+            interfaces
+        }
+
+        fn routes(&self) -> Vec<Route> {
+            Vec::new()
+        }
+
+        fn dns_servers(&self) -> Vec<DnsServer> {
+            Vec::new()
+        }
+    }
+
+    fn armed(grace_period_sec: u64) -> KillSwitchConfig {
+        KillSwitchConfig::from_settings(KillSwitchSettings {
+            enabled: true,
+            grace_period_sec,
+            ..Default::default()
+        })
+    }
+
+    #[test]
+    fn engages_at_once_and_releases_after_grace() {
+        let probe = FakeProbe::default();
+        let mut ks = armed(10);
+        probe.set(true);
+        assert!(advance(&mut ks, &probe, 1_000).is_none());
+
+        probe.set(false);
+        assert!(matches!(advance(&mut ks, &probe, 2_000), Some(KillSwitchState::Engaged)));
+        assert!(engaged(&ks));
+
+        probe.set(true);
+        assert!(matches!(advance(&mut ks, &probe, 3_000), Some(KillSwitchState::Releasing)));
+        assert!(engaged(&ks));
+        assert!(advance(&mut ks, &probe, 12_999).is_none());
+        assert!(matches!(advance(&mut ks, &probe, 13_000), Some(KillSwitchState::Armed)));
+        assert!(!engaged(&ks));
+    }
+
+    #[test]
+    fn flapping_link_restarts_the_grace_period() {
+        let probe = FakeProbe::default();
+        let mut ks = armed(5);
+        advance(&mut ks, &probe, 0);
+        for (at, up) in [(1_000, true), (2_000, false), (3_000, true), (4_000, false), (4_500, true)] {
+            probe.set(up);
+            advance(&mut ks, &probe, at);
+            assert!(engaged(&ks), "released while flapping at {at}");
+        }
+        assert!(advance(&mut ks, &probe, 9_000).is_none());
+        assert!(matches!(advance(&mut ks, &probe, 9_500), Some(KillSwitchState::Armed)));
+    }
+
+    #[test]
+    fn disabled_kill_switch_does_nothing() {
+        let probe = FakeProbe::default();
+        let mut ks = KillSwitchConfig::from_settings(KillSwitchSettings::default());
+        assert!(advance(&mut ks, &probe, 1_000).is_none());
+        assert!(matches!(ks.enforcement_state, KillSwitchState::Disarmed));
+        assert_eq!(actions(&ks), KillSwitchActions::default());
+    }
+
+    #[test]
+    fn each_trigger_maps_to_its_own_action() {
+        let probe = FakeProbe::default();
+        let mut ks = armed(0);
+        assert_eq!(actions(&ks), KillSwitchActions::default());
+        advance(&mut ks, &probe, 0);
+
+        ks.triggers.pause_all_torrents = false;
+        let only_listener = KillSwitchActions {
+            close_listener: true,
+            stop_scraping: true,
+            ..Default::default()
+        };
+        assert_eq!(actions(&ks), only_listener);
+
+        ks.triggers.stop_seeding = true;
+        assert_eq!(actions(&ks), KillSwitchActions { stop_seeding: true, ..only_listener });
+        ks.triggers.stop_seeding = false;
+
+        ks.triggers.disable_dht_pex_lpd = true;
+        assert_eq!(actions(&ks), KillSwitchActions { stop_discovery: true, ..only_listener });
+        ks.triggers.disable_dht_pex_lpd = false;
+
+        ks.triggers.block_outbound = true;
+        assert_eq!(actions(&ks), KillSwitchActions { block_outbound: true, ..only_listener });
+        ks.triggers.block_outbound = false;
+
+        ks.scope = KillSwitchScope::AppLevel;
+        let a = actions(&ks);
+        assert!(a.pause_torrents && a.stop_seeding && a.stop_discovery && a.block_outbound);
+    }
+
+    #[test]
+    fn leaks_are_judged_against_the_applied_actions() {
+        let start = TransferSample { live: true, downloaded: 100, uploaded: 50, live_peers: 0 };
+        let downloading = TransferSample { downloaded: 200, ..start };
+        let uploading = TransferSample { uploaded: 60, ..start };
+
+        let pause = KillSwitchActions { pause_torrents: true, ..Default::default() };
+        let paused = TransferSample { live: false, ..start };
+        assert!(!leaked(&pause, &paused, &paused));
+        assert!(leaked(&pause, &paused, &TransferSample { live: false, ..downloading }));
+        // Never paused, or resumed during the window, even without moving data yet.
+        assert!(leaked(&pause, &paused, &start));
+
+        let stop_seeding = KillSwitchActions { stop_seeding: true, ..Default::default() };
+        assert!(!leaked(&stop_seeding, &start, &downloading));
+        assert!(leaked(&stop_seeding, &start, &uploading));
+    }
+
+    #[tokio::test]
+    async fn dry_run_closes_the_listener_without_holding_the_state() {
+        let dir = std::env::temp_dir().join(format!("orc-ks-test-{}", uuid::Uuid::new_v4()));
+        let port = TcpListener::bind("0.0.0.0:0").unwrap().local_addr().unwrap().port();
+        let shared = crate::new_state(crate::SessionConfig {
+            download_dir: dir.to_string_lossy().into_owned(),
+            listen_port: port,
+            disable_dht: true,
+            ..Default::default()
+        })
+        .await
+        .unwrap();
+
+        let test = tokio::spawn({
+            let shared = shared.clone();
+            async move { test_kill_switch(&shared).await }
+        });
+        tokio::time::sleep(TEST_WINDOW / 3).await;
+        {
+            let state = tokio::time::timeout(Duration::from_millis(500), shared.lock())
+                .await
+                .expect("state stays locked during the test window");
+            assert!(engaged(&state.kill_switch));
+            assert_eq!(state.rqbit.session().listen_info().tcp_port, None);
+            assert!(state.rqbit.session().scraping_blocked());
+        }
+        assert!(test_kill_switch(&shared).await.is_err(), "a second test must wait");
+
+        let report = test.await.unwrap().unwrap();
+        assert!(report.ok, "{}", report.message);
+        assert!(!report.enabled && report.restored);
+        assert!(matches!(
+            report.closed_connections[..],
+            [KillSwitchConnection::PeerListener { port: p }] if p == port
+        ));
+        let state = shared.lock().await;
+        assert!(!state.kill_switch.enabled && !engaged(&state.kill_switch));
+        assert!(!state.kill_switch_enforcement.testing);
+        assert_eq!(state.rqbit.session().listen_info().tcp_port, Some(port));
+        assert!(!state.rqbit.session().scraping_blocked());
+        drop(state);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[tokio::test]
+    async fn dry_run_keeps_changes_made_during_the_window() {
+        let dir = std::env::temp_dir().join(format!("orc-ks-test-{}", uuid::Uuid::new_v4()));
+        let shared = crate::new_state(crate::SessionConfig {
+            download_dir: dir.to_string_lossy().into_owned(),
+            listen_port: 0,
+            disable_dht: true,
+            ..Default::default()
+        })
+        .await
+        .unwrap();
+
+        let test = tokio::spawn({
+            let shared = shared.clone();
+            async move { test_kill_switch(&shared).await }
+        });
+        tokio::time::sleep(TEST_WINDOW / 3).await;
+        {
+            let mut state = shared.lock().await;
+            let request = crate::PatchKillSwitchRequest {
+                enabled: None,
+                scope: Some(KillSwitchScope::AppLevel),
+                grace_period_sec: None,
+                triggers: None,
+            };
+            crate::patch_kill_switch(&mut state, request);
+            assert!(state.kill_switch.enabled);
+            assert!(!kill_switch_settings(&state).enabled, "the forced state must not be saved");
+        }
+        test.await.unwrap().unwrap();
+        {
+            let state = shared.lock().await;
+            assert!(!state.kill_switch.enabled);
+            assert!(matches!(state.kill_switch.scope, KillSwitchScope::AppLevel));
+        }
+
+        let mut state = shared.lock().await;
+        state.kill_switch.enabled = true;
+        state.kill_switch.enforcement_state = KillSwitchState::Armed;
+        drop(state);
+        let test = tokio::spawn({
+            let shared = shared.clone();
+            async move { test_kill_switch(&shared).await }
+        });
+        tokio::time::sleep(TEST_WINDOW / 3).await;
+        {
+            let mut state = shared.lock().await;
+            let request = crate::PatchKillSwitchRequest {
+                enabled: Some(false),
+                scope: None,
+                grace_period_sec: None,
+                triggers: None,
+            };
+            crate::patch_kill_switch(&mut state, request);
+            enforce_kill_switch(&mut state).await;
+        }
+        let report = test.await.unwrap().unwrap();
+        assert!(report.restored, "{}", report.message);
+        let state = shared.lock().await;
+        assert!(!state.kill_switch.enabled, "a disable made during the test was undone");
+        assert!(matches!(state.kill_switch.enforcement_state, KillSwitchState::Disarmed));
+        drop(state);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,312 @@
+//! Public IP leak check.
+//!
+//! Asks a user-configured IP-echo URL for our public address twice: through the default route,
+//! which is how torrent traffic leaves, and bound to the VPN interface. Two different answers
+//! mean torrent traffic egresses from a non-VPN address; that raises a `Block` policy warning
+//! and puts the network posture in `leak_risk`. No URL is configured by default, so nothing is
+//! contacted unless the user opts in.
+
+use std::net::IpAddr;
+use std::time::Duration;
+
+use anyhow::{anyhow, Context, Result};
+use serde::{Deserialize, Serialize};
+use librqbit::BindTarget;
+use tracing::{info, warn};
+use url::Url;
+
+use crate::{now_ms, set_policy_warning, vpn_status, OrcState, PolicyWarning, PolicyWarningSeverity, SharedState};
+
+const LEAK_WARNING: &str = "public_ip_leak";
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+/// Echo services answer with an address; anything longer is not one.
+const MAX_BODY_LEN: usize = 256;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LeakCheckSettings {
+    /// Answers a `GET` with the caller's IP, as plain text or JSON `{"ip": "..."}`. Unset
+    /// disables the check.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub url: Option<String>,
+    #[serde(default = "default_interval_sec")]
+    pub interval_sec: u64,
+}
+
+fn default_interval_sec() -> u64 {
+    300
+}
+
+impl Default for LeakCheckSettings {
+    fn default() -> Self {
+        Self {
+            url: None,
+            interval_sec: default_interval_sec(),
+        }
+    }
+}
+
+impl LeakCheckSettings {
+    pub fn validate(&self) -> Result<()> {
+        const MIN_INTERVAL_SEC: u64 = 30;
+        const MAX_INTERVAL_SEC: u64 = 24 * 60 * 60;
+        if !(MIN_INTERVAL_SEC..=MAX_INTERVAL_SEC).contains(&self.interval_sec) {
+            return Err(anyhow!(
+                "leak_check interval_sec must be between {} and {}",
+                MIN_INTERVAL_SEC,
+                MAX_INTERVAL_SEC
+            ));
+        }
+        if let Some(url) = &self.url {
+            parse_url(url)?;
+        }
+        Ok(())
+    }
+
+    fn url(&self) -> Option<Url> {
+        self.url.as_deref().and_then(|u| parse_url(u).ok())
+    }
+}
+
+fn parse_url(url: &str) -> Result<Url> {
+    let url = Url::parse(url.trim()).context("Invalid leak_check url")?;
+    if !matches!(url.scheme(), "http" | "https") {
+        return Err(anyhow!("leak_check url must be http or https"));
+    }
+    Ok(url)
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct LeakCheck {
+    pub checked_ms: u64,
+    /// Address the echo URL saw from where torrent traffic leaves: the default route, or the
+    /// `bind_interface` binding.
+    pub default_route_ip: Option<IpAddr>,
+    pub vpn_interface: Option<String>,
+    /// Address the echo URL saw through the VPN interface; `None` without a VPN.
+    pub vpn_ip: Option<IpAddr>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+impl LeakCheck {
+    /// `None` unless both lookups answered.
+    pub fn public_ip_match(&self) -> Option<bool> {
+        Some(self.default_route_ip? == self.vpn_ip?)
+    }
+
+    pub fn leaking(&self) -> bool {
+        self.public_ip_match() == Some(false)
+    }
+}
+
+fn parse_ip(body: &str) -> Result<IpAddr> {
+    let body = body.trim();
+    let text = if body.starts_with('{') {
+        let value: serde_json::Value = serde_json::from_str(body).context("Invalid JSON from IP echo")?;
+        value
+            .get("ip")
+            .and_then(|v| v.as_str())
+            .ok_or_else(|| anyhow!("IP echo JSON has no \"ip\" field"))?
+            .to_string()
+    } else {
+        body.to_string()
+    };
+    text.parse().with_context(|| format!("IP echo did not return an address: {:?}", text))
+}
+
+fn client(bind: Option<&BindTarget>) -> Result<reqwest::Client> {
+    // A proxy from the environment would answer with the proxy's address.
+    let builder = reqwest::Client::builder().timeout(REQUEST_TIMEOUT).no_proxy();
+    let builder = match bind {
+        None => builder,
+        Some(BindTarget::Address(ip)) => builder.local_address(*ip),
+        #[cfg(any(target_os = "linux", target_os = "android", target_os = "macos"))]
+        Some(BindTarget::Interface(name)) => builder.interface(name),
+        #[cfg(not(any(target_os = "linux", target_os = "android", target_os = "macos")))]
+        Some(BindTarget::Interface(name)) => builder.local_address(crate::vpn::interface_address(name)?),
+    };
+    builder.build().context("Failed to build IP echo client")
+}
+
+async fn fetch_ip(url: &Url, bind: Option<&BindTarget>) -> Result<IpAddr> {
+    let mut response = client(bind)?
+        .get(url.clone())
+        .send()
+        .await
+        .context("IP echo request failed")?
+        .error_for_status()
+        .context("IP echo returned an error")?;
+    if response.content_length().is_some_and(|len| len > MAX_BODY_LEN as u64) {
+        return Err(anyhow!("IP echo response is too long"));
+    }
+    // Read only up to the cap; a body without a length could otherwise be arbitrarily large.
+    let mut body = Vec::new();
+    while let Some(chunk) = response.chunk().await.context("Failed to read IP echo response")? {
+        if body.len() + chunk.len() > MAX_BODY_LEN {
+            return Err(anyhow!("IP echo response is too long"));
+        }
+        body.extend_from_slice(&chunk);
+    }
+    parse_ip(&String::from_utf8_lossy(&body))
+}
+
+/// Query `url` the way torrent traffic goes out (the default route, or `torrent_bind` when the
+/// session is bound), then through `vpn_interface` if there is one.
+pub async fn check(url: &Url, torrent_bind: Option<&BindTarget>, vpn_interface: Option<&str>) -> LeakCheck {
+    let mut errors = Vec::new();
+    let default_route_ip = fetch_ip(url, torrent_bind)
+        .await
+        .map_err(|e| match torrent_bind {
+            Some(bind) => errors.push(format!("{bind}: {e:#}")),
+            None => errors.push(format!("default route: {e:#}")),
+        })
+        .ok();
+    let vpn_ip = match vpn_interface {
+        Some(name) => fetch_ip(url, Some(&BindTarget::Interface(name.to_string())))
+            .await
+            .map_err(|e| errors.push(format!("{name}: {e:#}")))
+            .ok(),
+        None => None,
+    };
+    LeakCheck {
+        checked_ms: now_ms(),
+        default_route_ip,
+        vpn_interface: vpn_interface.map(str::to_string),
+        vpn_ip,
+        error: (!errors.is_empty()).then(|| errors.join("; ")),
+    }
+}
+
+/// Run one check with the current settings and record it. The state lock is not held while
+/// the requests are in flight.
+pub async fn run_leak_check(state: &SharedState) {
+    let (url, bind, vpn_interface) = {
+        let guard = state.lock().await;
+        let Some(url) = guard.leak_check_settings.url() else {
+            return;
+        };
+        (url, guard.bind.clone(), vpn_status(&guard).interface_name)
+    };
+    let result = check(&url, bind.as_ref(), vpn_interface.as_deref()).await;
+    let mut guard = state.lock().await;
+    // Turned off while the requests were in flight.
+    if guard.leak_check_settings.url().is_none() {
+        return;
+    }
+    record(&mut guard, result);
+}
+
+fn record(state: &mut OrcState, result: LeakCheck) {
+    let was_leaking = state.leak_check.as_ref().is_some_and(LeakCheck::leaking);
+    if let Some(e) = &result.error {
+        warn!("Leak check: {}", e);
+    }
+    match (was_leaking, result.leaking()) {
+        (false, true) => warn!(
+            "Leak check: traffic leaves from {:?} instead of the VPN address {:?}",
+            result.default_route_ip, result.vpn_ip
+        ),
+        (true, false) => info!("Leak check: traffic leaves through the VPN again"),
+        _ => {}
+    }
+    state.leak_check = Some(result);
+    refresh_warning(state);
+}
+
+/// The `Block` warning for the last check, if it found a leak.
+pub(crate) fn warning(state: &OrcState) -> Option<PolicyWarning> {
+    let check = state.leak_check.as_ref().filter(|c| c.leaking())?;
+    Some(PolicyWarning {
+        code: LEAK_WARNING.to_string(),
+        message: format!(
+            "Torrent traffic leaves from {} rather than the VPN address {}.",
+            check.default_route_ip.map(|ip| ip.to_string()).unwrap_or_default(),
+            check.vpn_ip.map(|ip| ip.to_string()).unwrap_or_default()
+        ),
+        severity: PolicyWarningSeverity::Block,
+    })
+}
+
+/// Bring the policy warnings in line with the last check.
+pub(crate) fn refresh_warning(state: &mut OrcState) {
+    let want = warning(state);
+    set_policy_warning(state, LEAK_WARNING, want);
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{Read, Write};
+    use std::net::TcpListener;
+
+    use super::*;
+
+    /// Stand-in IP echo answering each connection with the next address in `answers`.
+    fn echo_server(answers: Vec<&'static str>) -> Url {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = Url::parse(&format!("http://{}/", listener.local_addr().unwrap())).unwrap();
+        std::thread::spawn(move || {
+            for (answer, stream) in answers.into_iter().zip(listener.incoming()) {
+                let mut stream = stream.unwrap();
+                let mut request = [0u8; 1024];
+                let _ = stream.read(&mut request);
+                let response = format!(
+                    "HTTP/1.1 200 OK\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                    answer.len(),
+                    answer
+                );
+                stream.write_all(response.as_bytes()).unwrap();
+            }
+        });
+        url
+    }
+
+    #[test]
+    fn parses_plain_and_json_answers() {
+        assert_eq!(parse_ip("203.0.113.7\n").unwrap(), "203.0.113.7".parse::<IpAddr>().unwrap());
+        assert_eq!(parse_ip(r#"{"ip": "2001:db8::7"}"#).unwrap(), "2001:db8::7".parse::<IpAddr>().unwrap());
+        assert!(parse_ip("<html>").is_err());
+        assert!(LeakCheckSettings { url: Some("file:///etc/passwd".into()), ..Default::default() }
+            .validate()
+            .is_err());
+    }
+
+    #[tokio::test]
+    async fn default_route_only_without_vpn() {
+        let url = echo_server(vec!["203.0.113.7"]);
+        let check = check(&url, None, None).await;
+        assert_eq!(check.default_route_ip, Some("203.0.113.7".parse().unwrap()));
+        assert_eq!(check.vpn_ip, None);
+        assert_eq!(check.error, None);
+        assert_eq!(check.public_ip_match(), None);
+        assert!(!check.leaking());
+    }
+
+    #[tokio::test]
+    async fn overlong_answer_is_an_error() {
+        let url = echo_server(vec!["7".repeat(MAX_BODY_LEN + 1).leak()]);
+        let check = check(&url, None, None).await;
+        assert_eq!(check.default_route_ip, None);
+        assert!(check.error.unwrap().contains("too long"));
+    }
+
+    #[cfg(target_os = "linux")]
+    #[tokio::test]
+    async fn different_answers_through_the_vpn_are_a_leak() {
+        // Loopback stands in for the VPN interface.
+        let url = echo_server(vec!["203.0.113.7", "198.51.100.9"]);
+        let leak = check(&url, None, Some("lo")).await;
+        assert_eq!(leak.error, None);
+        assert_eq!(leak.vpn_ip, Some("198.51.100.9".parse().unwrap()));
+        assert!(leak.leaking());
+
+        let url = echo_server(vec!["198.51.100.9", "198.51.100.9"]);
+        let tunneled = check(&url, None, Some("lo")).await;
+        assert_eq!(tunneled.public_ip_match(), Some(true));
+
+        // Bound to the VPN, torrent traffic is asked about from there as well.
+        let url = echo_server(vec!["198.51.100.9", "198.51.100.9"]);
+        let bound = check(&url, Some(&BindTarget::Interface("lo".into())), Some("lo")).await;
+        assert_eq!(bound.error, None);
+        assert_eq!(bound.default_route_ip, Some("198.51.100.9".parse().unwrap()));
+    }
+}
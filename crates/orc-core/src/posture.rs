@@ -0,0 +1,323 @@
+//! Network posture: whether torrent traffic is kept on the VPN.
+//!
+//! [`signals`] collects what the daemon knows (VPN detection and routes, the `bind_interface`
+//! binding, the kill switch, the peer listener, the IPv6 policy and the last leak check) into
+//! [`PostureSignals`]. [`evaluate`] is a pure function of those signals returning the
+//! [`NetPostureState`] and the reasons for it. `tick` re-evaluates every second and [`record`]
+//! keeps the time of the last actual change.
+
+use librqbit::BindTarget;
+use tracing::info;
+
+use crate::{kill_switch, vpn, vpn_status, KillSwitchState, LeakCheck, NetPostureState, OrcState};
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PostureSignals {
+    /// A VPN adapter is up.
+    pub vpn_connected: bool,
+    /// IPv4 internet traffic (IPv6 if there is no IPv4 route) is routed through the VPN.
+    /// `None` when the routing table is not read on this platform.
+    pub default_route_via_vpn: Option<bool>,
+    /// IPv6 internet traffic has a route, and it is not through the VPN.
+    pub ipv6_outside_vpn: bool,
+    /// `bind_interface`, if set.
+    pub bind: Option<String>,
+    /// The binding is a VPN adapter or an address of the connected one.
+    pub bound_to_vpn: bool,
+    /// Enabled and watching the VPN (`armed`, `engaged` or `releasing`).
+    pub kill_switch_armed: bool,
+    pub kill_switch_engaged: bool,
+    /// An engaged kill switch pauses torrents rather than only limiting them.
+    pub kill_switch_pauses: bool,
+    pub listener_open: bool,
+    /// IPv6 peers are allowed by the policy.
+    pub ipv6_enabled: bool,
+    /// The last leak check saw torrent traffic leave from a non-VPN address.
+    pub leak_detected: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PostureEvaluation {
+    pub state: NetPostureState,
+    /// Traffic stays off other routes even if the VPN drops: it is bound to the VPN, or an armed
+    /// kill switch pauses torrents.
+    pub leak_proof: bool,
+    /// Why the state is what it is; risks first.
+    pub reasons: Vec<String>,
+}
+
+pub fn evaluate(s: &PostureSignals) -> PostureEvaluation {
+    if !s.vpn_connected && s.bind.is_none() && !s.kill_switch_armed && !s.leak_detected {
+        return PostureEvaluation {
+            state: NetPostureState::Unconfigured,
+            leak_proof: false,
+            reasons: vec!["No VPN is connected, bind_interface is not set and the kill switch is off".to_string()],
+        };
+    }
+
+    let kill_switch_guards = s.kill_switch_armed && s.kill_switch_pauses;
+    let leak_proof = s.bound_to_vpn || kill_switch_guards;
+    let mut risks = Vec::new();
+    let mut ok = Vec::new();
+
+    if s.leak_detected {
+        risks.push("The leak check saw torrent traffic leave from a non-VPN address".to_string());
+    }
+
+    match (&s.bind, s.bound_to_vpn) {
+        (Some(bind), true) => ok.push(format!("Torrent traffic is bound to the VPN ({bind})")),
+        (Some(bind), false) => risks.push(format!("Torrent traffic is bound to {bind}, which is not the VPN")),
+        (None, _) => {}
+    }
+
+    if s.vpn_connected {
+        if s.bind.is_none() {
+            match s.default_route_via_vpn {
+                Some(true) => ok.push("The default route goes through the VPN".to_string()),
+                Some(false) => risks.push("The default route does not go through the VPN".to_string()),
+                None => ok.push("The VPN is connected; routes are not checked on this platform".to_string()),
+            }
+        }
+        if s.ipv6_outside_vpn && s.ipv6_enabled && !s.bound_to_vpn {
+            risks.push("IPv6 traffic is routed outside the VPN; disable IPv6 in the policy".to_string());
+        }
+    } else if s.kill_switch_engaged && s.kill_switch_pauses {
+        ok.push("The VPN is down and the kill switch has paused torrents".to_string());
+    } else if !s.bound_to_vpn {
+        risks.push("The VPN is not connected".to_string());
+    }
+
+    if kill_switch_guards {
+        ok.push("The kill switch is armed".to_string());
+    } else if s.kill_switch_armed && !s.bound_to_vpn {
+        risks.push("The kill switch does not pause torrents, so downloads go on if the VPN drops".to_string());
+    } else if !leak_proof {
+        risks.push("Nothing stops torrent traffic if the VPN drops: enable the kill switch or set bind_interface".to_string());
+    }
+
+    if s.listener_open && !s.bound_to_vpn {
+        risks.push("The peer listener accepts connections on every interface, not only the VPN".to_string());
+    }
+
+    let state = if risks.is_empty() { NetPostureState::Protected } else { NetPostureState::LeakRisk };
+    risks.extend(ok);
+    PostureEvaluation { state, leak_proof, reasons: risks }
+}
+
+pub(crate) fn signals(state: &OrcState) -> PostureSignals {
+    let vpn = vpn_status(state);
+    let vpn_interface = vpn.interface_name.as_deref().filter(|_| vpn::connected(&vpn));
+    let egress = vpn::egress_interfaces(&*state.vpn_probe);
+    let via_vpn = |interface: &Option<String>| interface.is_some() && interface.as_deref() == vpn_interface;
+    let bound_to_vpn = match &state.bind {
+        None => false,
+        Some(BindTarget::Interface(name)) => {
+            Some(name.as_str()) == vpn_interface || vpn::is_vpn_adapter(&state.kill_switch.vpn_source, name)
+        }
+        Some(BindTarget::Address(ip)) => vpn_interface.is_some_and(|i| vpn::interface_addresses(i).contains(ip)),
+    };
+    let ks = &state.kill_switch;
+    PostureSignals {
+        vpn_connected: vpn_interface.is_some(),
+        default_route_via_vpn: egress.as_ref().map(|(v4, v6)| via_vpn(if v4.is_some() { v4 } else { v6 })),
+        ipv6_outside_vpn: egress.as_ref().is_some_and(|(_, v6)| v6.is_some() && !via_vpn(v6)),
+        bind: state.bind.as_ref().map(BindTarget::to_string),
+        bound_to_vpn,
+        kill_switch_armed: ks.enabled && !matches!(ks.enforcement_state, KillSwitchState::Disarmed),
+        kill_switch_engaged: kill_switch::engaged(ks),
+        kill_switch_pauses: kill_switch::pauses_torrents(ks),
+        listener_open: state.rqbit.session().listen_info().tcp_port.is_some(),
+        ipv6_enabled: state.policy.effective.ipv6_enabled,
+        leak_detected: state.leak_check.as_ref().is_some_and(LeakCheck::leaking),
+    }
+}
+
+/// The current evaluation and when its state last changed.
+#[derive(Debug, Clone)]
+pub(crate) struct PostureRecord {
+    pub evaluation: PostureEvaluation,
+    pub last_change_ms: u64,
+}
+
+impl PostureRecord {
+    pub fn new(evaluation: PostureEvaluation, now_ms: u64) -> Self {
+        Self { evaluation, last_change_ms: now_ms }
+    }
+}
+
+/// Store `evaluation`; `last_change_ms` only moves when the state does. Returns the previous
+/// state on a transition.
+pub(crate) fn record(
+    record: &mut PostureRecord,
+    evaluation: PostureEvaluation,
+    now_ms: u64,
+) -> Option<NetPostureState> {
+    let previous = record.evaluation.state;
+    record.evaluation = evaluation;
+    if previous == record.evaluation.state {
+        return None;
+    }
+    record.last_change_ms = now_ms;
+    Some(previous)
+}
+
+/// Re-evaluate from the current state; called from `tick`.
+pub(crate) fn refresh(state: &mut OrcState, now_ms: u64) {
+    let evaluation = evaluate(&signals(state));
+    if let Some(previous) = record(&mut state.posture, evaluation, now_ms) {
+        let current = &state.posture.evaluation;
+        info!(
+            "Network posture {:?} -> {:?}: {}",
+            previous,
+            current.state,
+            current.reasons.join("; ")
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Every combination of the boolean signals, with the route signal in each of its states.
+    fn all_signals() -> Vec<PostureSignals> {
+        const FLAGS: u32 = 10;
+        let mut out = Vec::new();
+        for bits in 0..1u32 << FLAGS {
+            let bit = |i: u32| bits & (1 << i) != 0;
+            for default_route_via_vpn in [None, Some(false), Some(true)] {
+                out.push(PostureSignals {
+                    vpn_connected: bit(0),
+                    default_route_via_vpn,
+                    ipv6_outside_vpn: bit(1),
+                    bind: bit(2).then(|| "wg0".to_string()),
+                    bound_to_vpn: bit(2) && bit(3),
+                    kill_switch_armed: bit(4),
+                    kill_switch_engaged: bit(4) && bit(5),
+                    kill_switch_pauses: bit(6),
+                    listener_open: bit(7),
+                    ipv6_enabled: bit(8),
+                    leak_detected: bit(9),
+                });
+            }
+        }
+        out
+    }
+
+    #[test]
+    fn every_combination_is_consistent() {
+        for s in all_signals() {
+            let e = evaluate(&s);
+            assert!(!e.reasons.is_empty(), "{s:?}");
+            let configured = s.vpn_connected || s.bind.is_some() || s.kill_switch_armed || s.leak_detected;
+            assert_eq!(e.state == NetPostureState::Unconfigured, !configured, "{s:?}");
+            if s.leak_detected {
+                assert_eq!(e.state, NetPostureState::LeakRisk, "{s:?}");
+            }
+            if e.state == NetPostureState::Protected {
+                // Something keeps traffic on the VPN now and if it drops.
+                assert!(e.leak_proof, "{s:?}");
+                assert!(s.vpn_connected || s.bound_to_vpn || s.kill_switch_engaged, "{s:?}");
+                assert!(!s.listener_open || s.bound_to_vpn, "{s:?}");
+            }
+            assert_eq!(e.leak_proof, s.bound_to_vpn || (s.kill_switch_armed && s.kill_switch_pauses), "{s:?}");
+        }
+    }
+
+    fn protected_by_kill_switch() -> PostureSignals {
+        PostureSignals {
+            vpn_connected: true,
+            default_route_via_vpn: Some(true),
+            kill_switch_armed: true,
+            kill_switch_pauses: true,
+            ipv6_enabled: true,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn each_risk_is_reported() {
+        let base = protected_by_kill_switch();
+        let e = evaluate(&base);
+        assert_eq!(e.state, NetPostureState::Protected);
+        assert_eq!(e.reasons, ["The default route goes through the VPN", "The kill switch is armed"]);
+
+        let cases: [(PostureSignals, &str); 6] = [
+            (PostureSignals { listener_open: true, ..base.clone() }, "peer listener"),
+            (PostureSignals { ipv6_outside_vpn: true, ..base.clone() }, "IPv6"),
+            (PostureSignals { default_route_via_vpn: Some(false), ..base.clone() }, "default route does not"),
+            (PostureSignals { kill_switch_pauses: false, ..base.clone() }, "does not pause"),
+            (PostureSignals { leak_detected: true, ..base.clone() }, "leak check"),
+            (PostureSignals { bind: Some("eth0".into()), ..base.clone() }, "not the VPN"),
+        ];
+        for (signals, reason) in cases {
+            let e = evaluate(&signals);
+            assert_eq!(e.state, NetPostureState::LeakRisk, "{reason}");
+            assert!(e.reasons[0].contains(reason), "{reason}: {:?}", e.reasons);
+        }
+
+        // IPv6 outside the VPN is fine once IPv6 peers are off.
+        let v6_off = PostureSignals { ipv6_outside_vpn: true, ipv6_enabled: false, ..base };
+        assert_eq!(evaluate(&v6_off).state, NetPostureState::Protected);
+    }
+
+    #[test]
+    fn binding_to_the_vpn_protects_without_kill_switch() {
+        let bound = PostureSignals {
+            vpn_connected: true,
+            default_route_via_vpn: Some(false),
+            ipv6_outside_vpn: true,
+            ipv6_enabled: true,
+            listener_open: true,
+            bind: Some("wg0".into()),
+            bound_to_vpn: true,
+            ..Default::default()
+        };
+        let e = evaluate(&bound);
+        assert_eq!(e.state, NetPostureState::Protected);
+        assert!(e.leak_proof);
+
+        // Still protected while the VPN is down: the sockets cannot use another route.
+        let down = PostureSignals { vpn_connected: false, listener_open: false, ..bound };
+        assert_eq!(evaluate(&down).state, NetPostureState::Protected);
+    }
+
+    #[test]
+    fn vpn_drop_follows_the_kill_switch() {
+        let engaged = PostureSignals {
+            vpn_connected: false,
+            default_route_via_vpn: Some(false),
+            kill_switch_engaged: true,
+            ..protected_by_kill_switch()
+        };
+        assert_eq!(evaluate(&engaged).state, NetPostureState::Protected);
+
+        let downloading = PostureSignals { kill_switch_pauses: false, ..engaged };
+        let e = evaluate(&downloading);
+        assert_eq!(e.state, NetPostureState::LeakRisk);
+        assert_eq!(e.reasons[0], "The VPN is not connected");
+
+        let unconfigured = evaluate(&PostureSignals { listener_open: true, ..Default::default() });
+        assert_eq!(unconfigured.state, NetPostureState::Unconfigured);
+        assert!(!unconfigured.leak_proof);
+    }
+
+    #[test]
+    fn transitions_keep_their_timestamp() {
+        let protected = evaluate(&protected_by_kill_switch());
+        let mut rec = PostureRecord::new(protected.clone(), 1_000);
+
+        assert_eq!(record(&mut rec, protected.clone(), 2_000), None);
+        assert_eq!(rec.last_change_ms, 1_000);
+
+        let leaking = evaluate(&PostureSignals { leak_detected: true, ..protected_by_kill_switch() });
+        assert_eq!(record(&mut rec, leaking.clone(), 3_000), Some(NetPostureState::Protected));
+        assert_eq!(rec.last_change_ms, 3_000);
+
+        // New reasons under the same state are stored without counting as a change.
+        let more = evaluate(&PostureSignals { leak_detected: true, listener_open: true, ..protected_by_kill_switch() });
+        assert_eq!(record(&mut rec, more.clone(), 4_000), None);
+        assert_eq!(rec.evaluation, more);
+        assert_eq!(rec.last_change_ms, 3_000);
+    }
+}
@@ -0,0 +1,288 @@
+//! Durable torrent registry.
+//!
+//! Mirrors orc's own view of every torrent (UUID, name, profile, file priorities, save path)
+//! to `torrents.json` next to the daemon's `config.json`. Each torrent's metainfo is kept in
+//! `torrents/<info_hash>.torrent` so the rqbit session can be rebuilt at startup without
+//! resolving magnets again.
+
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Deserializer, Serialize};
+
+use crate::TorrentProfile;
+
+const REGISTRY_VERSION: u32 = 2;
+// Registries older than this did not keep trackers by their real tiers.
+const TIERED_TRACKERS_VERSION: u32 = 2;
+const METAINFO_DIR_NAME: &str = "torrents";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub(crate) struct PersistedFile {
+    pub path: Vec<String>,
+    pub size: u64,
+    pub priority: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub(crate) struct PersistedTorrent {
+    pub id: String,
+    pub name: String,
+    pub added_at_ms: u64,
+    pub running: bool,
+    pub profile: TorrentProfile,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub info_hash_hex: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub save_path: Option<String>,
+    #[serde(default)]
+    pub files: Vec<PersistedFile>,
+    /// The torrent's trackers by BEP 12 tier.
+    #[serde(default, deserialize_with = "deserialize_tracker_tiers")]
+    pub trackers: Vec<Vec<String>>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub labels: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub completed_dir: Option<String>,
+    /// Original magnet link; only used when no metainfo was stored for this torrent.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub magnet: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct RegistryFile {
+    version: u32,
+    torrents: Vec<PersistedTorrent>,
+}
+
+#[derive(Debug, Clone)]
+pub(crate) struct TorrentRegistry {
+    path: PathBuf,
+    metainfo_dir: PathBuf,
+}
+
+impl TorrentRegistry {
+    pub fn new(path: PathBuf) -> Self {
+        let metainfo_dir = path
+            .parent()
+            .map(|p| p.join(METAINFO_DIR_NAME))
+            .unwrap_or_else(|| PathBuf::from(METAINFO_DIR_NAME));
+        Self { path, metainfo_dir }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Load all persisted torrents. A missing registry file is an empty registry.
+    pub fn load(&self) -> Result<Vec<PersistedTorrent>> {
+        let content = match std::fs::read_to_string(&self.path) {
+            Ok(c) => c,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(vec![]),
+            Err(e) => return Err(e).context("Failed to read torrent registry"),
+        };
+        let file: RegistryFile =
+            serde_json::from_str(&content).context("Failed to parse torrent registry")?;
+        if file.version > REGISTRY_VERSION {
+            return Err(anyhow::anyhow!(
+                "Torrent registry version {} is newer than supported version {}",
+                file.version,
+                REGISTRY_VERSION
+            ));
+        }
+        let mut torrents = file.torrents;
+        if file.version < TIERED_TRACKERS_VERSION {
+            for t in &mut torrents {
+                t.trackers = self.retier_trackers(t);
+            }
+        }
+        Ok(torrents)
+    }
+
+    // Put trackers saved without their tiers, flat or one tier each, back into the tiers of
+    // the stored metainfo, or of the magnet. Trackers neither lists were added later and go
+    // last, one tier each; ones they list that were not saved had been removed.
+    fn retier_trackers(&self, t: &PersistedTorrent) -> Vec<Vec<String>> {
+        let source = match t.info_hash_hex.as_deref().and_then(|h| self.load_metainfo(h)) {
+            Some(bytes) => crate::parse_trackers_from_torrent_bytes(&bytes),
+            None => t
+                .magnet
+                .as_deref()
+                .map(|m| vec![crate::parse_trackers_from_magnet(m)])
+                .unwrap_or_default(),
+        };
+        let saved = t.trackers.iter().flatten().collect::<Vec<_>>();
+        let mut tiers = source
+            .into_iter()
+            .map(|tier| tier.into_iter().filter(|u| saved.contains(&u)).collect::<Vec<_>>())
+            .collect::<Vec<_>>();
+        let added = saved
+            .iter()
+            .filter(|u| !tiers.iter().flatten().any(|listed| listed == **u))
+            .map(|u| vec![u.to_string()])
+            .collect::<Vec<_>>();
+        tiers.extend(added);
+        crate::dedup_tiers(tiers)
+    }
+
+    /// Atomically replace the registry contents (write to a temp file, then rename).
+    pub fn save(&self, torrents: &[PersistedTorrent]) -> Result<()> {
+        if let Some(parent) = self.path.parent() {
+            std::fs::create_dir_all(parent).context("Failed to create registry directory")?;
+        }
+        let file = RegistryFile {
+            version: REGISTRY_VERSION,
+            torrents: torrents.to_vec(),
+        };
+        let content = serde_json::to_vec_pretty(&file).context("Failed to serialize torrent registry")?;
+        let tmp = self.path.with_extension("json.tmp");
+        std::fs::write(&tmp, content).context("Failed to write torrent registry")?;
+        restrict_permissions(&tmp)?;
+        std::fs::rename(&tmp, &self.path).context("Failed to replace torrent registry")?;
+        Ok(())
+    }
+
+    pub fn store_metainfo(&self, info_hash: &str, bytes: &[u8]) -> Result<()> {
+        let path = self.metainfo_path(info_hash)?;
+        std::fs::create_dir_all(&self.metainfo_dir).context("Failed to create metainfo directory")?;
+        std::fs::write(&path, bytes).context("Failed to write torrent metainfo")?;
+        restrict_permissions(&path)
+    }
+
+    pub fn load_metainfo(&self, info_hash: &str) -> Option<Vec<u8>> {
+        let path = self.metainfo_path(info_hash).ok()?;
+        std::fs::read(path).ok()
+    }
+
+    pub fn remove_metainfo(&self, info_hash: &str) -> Result<()> {
+        let path = self.metainfo_path(info_hash)?;
+        match std::fs::remove_file(path) {
+            Ok(()) => Ok(()),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(()),
+            Err(e) => Err(e).context("Failed to remove torrent metainfo"),
+        }
+    }
+
+    fn metainfo_path(&self, info_hash: &str) -> Result<PathBuf> {
+        // Security: info hash becomes a filename, so only accept plain hex.
+        if info_hash.len() != 40 || !info_hash.chars().all(|c| c.is_ascii_hexdigit()) {
+            return Err(anyhow::anyhow!("Invalid info hash for metainfo path"));
+        }
+        Ok(self
+            .metainfo_dir
+            .join(format!("{}.torrent", info_hash.to_lowercase())))
+    }
+}
+
+// Registries written before trackers had tiers hold a flat list; each entry becomes a tier
+// until [`TorrentRegistry::load`] puts them back into the metainfo's tiers.
+fn deserialize_tracker_tiers<'de, D>(deserializer: D) -> Result<Vec<Vec<String>>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Trackers {
+        Tiers(Vec<Vec<String>>),
+        Flat(Vec<String>),
+    }
+    Ok(match Trackers::deserialize(deserializer)? {
+        Trackers::Tiers(tiers) => tiers,
+        Trackers::Flat(urls) => urls.into_iter().map(|u| vec![u]).collect(),
+    })
+}
+
+fn restrict_permissions(path: &Path) -> Result<()> {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        std::fs::set_permissions(path, std::fs::Permissions::from_mode(0o600))
+            .context("Failed to set registry file permissions")?;
+    }
+    #[cfg(not(unix))]
+    let _ = path;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::TorrentMode;
+
+    fn temp_registry() -> TorrentRegistry {
+        let dir = std::env::temp_dir().join(format!("orc-registry-{}", uuid::Uuid::new_v4()));
+        TorrentRegistry::new(dir.join("torrents.json"))
+    }
+
+    #[test]
+    fn missing_registry_loads_empty() {
+        let reg = temp_registry();
+        assert!(reg.load().expect("load").is_empty());
+    }
+
+    #[test]
+    fn registry_round_trips_torrents_and_metainfo() {
+        let reg = temp_registry();
+        let hash = "0123456789abcdef0123456789abcdef01234567";
+        let t = PersistedTorrent {
+            id: "4b1d6a53-7f2e-4c43-9d8c-0f4f1c6b2a10".to_string(),
+            name: "example".to_string(),
+            added_at_ms: 42,
+            running: false,
+            profile: TorrentProfile { mode: TorrentMode::Private, hops: 2 },
+            info_hash_hex: Some(hash.to_string()),
+            save_path: Some("/data/example".to_string()),
+            files: vec![PersistedFile {
+                path: vec!["a".to_string(), "b.bin".to_string()],
+                size: 10,
+                priority: "skip".to_string(),
+            }],
+            trackers: vec![vec!["udp://tracker.example:1337/announce".to_string()]],
+            labels: vec!["linux".to_string()],
+            completed_dir: Some("/data/done".to_string()),
+            magnet: None,
+        };
+        reg.save(std::slice::from_ref(&t)).expect("save");
+        reg.store_metainfo(hash, b"d4:infod4:name1:xee").expect("store metainfo");
+
+        let loaded = reg.load().expect("load");
+        assert_eq!(loaded.len(), 1);
+        assert_eq!(loaded[0].id, t.id);
+        assert_eq!(loaded[0].files[0].priority, "skip");
+        assert_eq!(loaded[0].save_path.as_deref(), Some("/data/example"));
+        assert_eq!(loaded[0].completed_dir.as_deref(), Some("/data/done"));
+        assert_eq!(loaded[0].labels, vec!["linux".to_string()]);
+        assert_eq!(loaded[0].trackers, t.trackers);
+        assert_eq!(reg.load_metainfo(hash).as_deref(), Some(&b"d4:infod4:name1:xee"[..]));
+
+        reg.remove_metainfo(hash).expect("remove metainfo");
+        assert!(reg.load_metainfo(hash).is_none());
+        let _ = std::fs::remove_dir_all(reg.path().parent().unwrap());
+    }
+
+    #[test]
+    fn flat_tracker_lists_load_as_one_tier_each() {
+        let p: PersistedTorrent = serde_json::from_value(serde_json::json!({
+            "id": "x",
+            "name": "x",
+            "added_at_ms": 0,
+            "running": true,
+            "profile": {"mode": "standard", "hops": 0},
+            "trackers": ["udp://a.example:1/announce", "http://b.example/announce"],
+        }))
+        .expect("parse");
+        assert_eq!(
+            p.trackers,
+            vec![
+                vec!["udp://a.example:1/announce".to_string()],
+                vec!["http://b.example/announce".to_string()],
+            ]
+        );
+    }
+
+    #[test]
+    fn metainfo_path_rejects_non_hex() {
+        let reg = temp_registry();
+        assert!(reg.store_metainfo("../../etc/passwd", b"x").is_err());
+    }
+}
@@ -0,0 +1,145 @@
+//! Trash for torrents removed with `delete_data=trash`.
+//!
+//! Each removal gets its own `<removed_at_ms>-<orc id>` folder holding the torrent's files
+//! under their original relative paths. Folders older than the retention period are purged.
+
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use anyhow::{anyhow, Context, Result};
+use serde::{Deserialize, Serialize};
+
+use crate::now_ms;
+
+const DEFAULT_TRASH_DIR_NAME: &str = ".orc-trash";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TrashSettings {
+    /// Trash folder; unset means `.orc-trash` inside the download directory, which keeps the
+    /// move a rename for torrents saved there.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub dir: Option<String>,
+    /// Days a removed torrent's files are kept before they are deleted for good.
+    #[serde(default = "default_retention_days")]
+    pub retention_days: u32,
+}
+
+fn default_retention_days() -> u32 {
+    7
+}
+
+impl Default for TrashSettings {
+    fn default() -> Self {
+        Self {
+            dir: None,
+            retention_days: default_retention_days(),
+        }
+    }
+}
+
+impl TrashSettings {
+    pub fn validate(&self) -> Result<()> {
+        const MAX_RETENTION_DAYS: u32 = 365;
+        if self.retention_days > MAX_RETENTION_DAYS {
+            return Err(anyhow!("trash retention_days must be at most {}", MAX_RETENTION_DAYS));
+        }
+        if let Some(dir) = &self.dir {
+            if !Path::new(dir.trim()).is_absolute() {
+                return Err(anyhow!("trash dir must be an absolute path"));
+            }
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Trash {
+    dir: PathBuf,
+    retention: Duration,
+}
+
+impl Trash {
+    pub(crate) fn new(settings: &TrashSettings, download_dir: &Path) -> Self {
+        let dir = settings
+            .dir
+            .as_deref()
+            .map(|d| PathBuf::from(d.trim()))
+            .unwrap_or_else(|| download_dir.join(DEFAULT_TRASH_DIR_NAME));
+        Self {
+            dir,
+            retention: Duration::from_secs(u64::from(settings.retention_days) * 24 * 60 * 60),
+        }
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Folder for a torrent being removed now.
+    pub fn entry_for(&self, id: &str) -> PathBuf {
+        self.dir.join(format!("{}-{}", now_ms(), id))
+    }
+
+    /// Delete entries older than the retention period. Returns how many were removed.
+    pub fn purge_expired(&self) -> Result<usize> {
+        let entries = match std::fs::read_dir(&self.dir) {
+            Ok(e) => e,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(0),
+            Err(e) => return Err(e).context("Failed to read trash directory"),
+        };
+        let cutoff = now_ms().saturating_sub(self.retention.as_millis() as u64);
+        let mut purged = 0;
+        for entry in entries.flatten() {
+            let name = entry.file_name();
+            // Only touch folders we created.
+            let Some(removed_at) = name
+                .to_str()
+                .and_then(|n| n.split_once('-'))
+                .and_then(|(ms, _)| ms.parse::<u64>().ok())
+            else {
+                continue;
+            };
+            if removed_at > cutoff {
+                continue;
+            }
+            match std::fs::remove_dir_all(entry.path()) {
+                Ok(()) => purged += 1,
+                Err(e) => tracing::warn!("Failed to purge trash entry {:?}: {}", name, e),
+            }
+        }
+        Ok(purged)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn purge_removes_only_expired_entries() {
+        let root = std::env::temp_dir().join(format!("orc-trash-{}", uuid::Uuid::new_v4()));
+        let trash = Trash::new(
+            &TrashSettings { dir: Some(root.to_string_lossy().into_owned()), retention_days: 1 },
+            Path::new("/unused"),
+        );
+        let old = root.join(format!("{}-old", now_ms() - 2 * 24 * 60 * 60 * 1000));
+        let fresh = trash.entry_for("fresh");
+        let foreign = root.join("not-ours");
+        for dir in [&old, &fresh, &foreign] {
+            std::fs::create_dir_all(dir.join("sub")).unwrap();
+            std::fs::write(dir.join("sub/file"), b"x").unwrap();
+        }
+
+        assert_eq!(trash.purge_expired().unwrap(), 1);
+        assert!(!old.exists());
+        assert!(fresh.exists());
+        assert!(foreign.exists());
+        let _ = std::fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn default_dir_is_inside_downloads() {
+        let trash = Trash::new(&TrashSettings::default(), Path::new("/data/downloads"));
+        assert_eq!(trash.dir(), Path::new("/data/downloads/.orc-trash"));
+    }
+}
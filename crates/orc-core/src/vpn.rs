@@ -0,0 +1,598 @@
+//! VPN detection.
+//!
+//! A [`VpnProbe`] reports the raw inputs (interfaces that have an address, the routing table
+//! and the DNS resolvers in use) and [`status`] turns them into a [`VpnStatus`] for the
+//! configured [`VpnSource`]:
+//! - `adapter_match`: an interface is on the `allowed_adapters` list or, when the list is
+//!   empty and `auto_detect` is on, its name looks like a VPN adapter;
+//! - `default_route_match`: internet traffic, IPv4 and IPv6 alike, is routed through it;
+//! - `dns_match`: every resolver is reached through it.
+//!
+//! [`LinuxVpnProbe`] reads procfs, `resolv.conf` and systemd-resolved's link state under a
+//! root directory, so tests can point it at fixture trees. Other platforms only report
+//! interfaces, which leaves the route and DNS signals false.
+//!
+//! The `bind_interface` setting, which pins torrent traffic to one interface, is parsed here
+//! too.
+
+use std::collections::HashMap;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use anyhow::{anyhow, Result};
+use librqbit::BindTarget;
+use network_interface::{NetworkInterface, NetworkInterfaceConfig};
+use regex::Regex;
+
+use crate::{ConnectionType, VpnPostureState, VpnSignals, VpnSource, VpnStatus};
+
+/// Documentation addresses standing in for "the internet": only looked up in the routing
+/// table, never contacted.
+const INTERNET_V4: Ipv4Addr = Ipv4Addr::new(198, 51, 100, 1);
+const INTERNET_V6: Ipv6Addr = Ipv6Addr::new(0x2001, 0xdb8, 0, 0, 0, 0, 0, 1);
+
+/// `RTF_UP` and `RTF_REJECT` from `linux/route.h`.
+const RTF_UP: u32 = 0x0001;
+const RTF_REJECT: u32 = 0x0200;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Route {
+    pub interface: String,
+    pub destination: IpAddr,
+    pub prefix_len: u8,
+    pub metric: u32,
+}
+
+impl Route {
+    fn contains(&self, addr: IpAddr) -> bool {
+        match (self.destination, addr) {
+            (IpAddr::V4(dest), IpAddr::V4(addr)) => {
+                let mask = u32::MAX.checked_shl(32 - u32::from(self.prefix_len)).unwrap_or(0);
+                u32::from(dest) & mask == u32::from(addr) & mask
+            }
+            (IpAddr::V6(dest), IpAddr::V6(addr)) => {
+                let mask = u128::MAX.checked_shl(128 - u32::from(self.prefix_len)).unwrap_or(0);
+                u128::from(dest) & mask == u128::from(addr) & mask
+            }
+            _ => false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DnsServer {
+    pub addr: IpAddr,
+    /// Link the resolver is configured on, when systemd-resolved says so.
+    pub interface: Option<String>,
+}
+
+/// Source of the inputs VPN detection runs on.
+pub trait VpnProbe: Send + Sync {
+    /// Names of the interfaces that have at least one address.
+    fn interfaces(&self) -> Vec<String>;
+    /// The main routing table, IPv4 and IPv6.
+    fn routes(&self) -> Vec<Route>;
+    fn dns_servers(&self) -> Vec<DnsServer>;
+}
+
+/// The probe for the platform the daemon runs on.
+pub fn system_probe() -> Arc<dyn VpnProbe> {
+    if cfg!(target_os = "linux") {
+        Arc::new(LinuxVpnProbe::new())
+    } else {
+        Arc::new(InterfaceVpnProbe)
+    }
+}
+
+fn interfaces_with_address() -> Vec<String> {
+    NetworkInterface::show()
+        .unwrap_or_default()
+        .into_iter()
+        .filter(|i| !i.addr.is_empty())
+        .map(|i| i.name)
+        .collect()
+}
+
+pub(crate) fn interface_addresses(name: &str) -> Vec<IpAddr> {
+    NetworkInterface::show()
+        .unwrap_or_default()
+        .into_iter()
+        .filter(|i| i.name == name)
+        .flat_map(|i| i.addr.into_iter().map(|a| a.ip()))
+        .collect()
+}
+
+/// Index of interface `name`. Deleting and re-creating the interface gives it a new one.
+pub(crate) fn interface_index(name: &str) -> Option<u32> {
+    NetworkInterface::show()
+        .unwrap_or_default()
+        .into_iter()
+        .find(|i| i.name == name)
+        .map(|i| i.index)
+}
+
+/// First address of interface `name`, IPv4 preferred.
+pub(crate) fn interface_address(name: &str) -> Result<IpAddr> {
+    let addrs = interface_addresses(name);
+    addrs
+        .iter()
+        .find(|ip| ip.is_ipv4())
+        .or(addrs.first())
+        .copied()
+        .ok_or_else(|| anyhow!("Interface {} has no address", name))
+}
+
+/// Parse the `bind_interface` setting: an IP address, or else an interface name.
+pub fn parse_bind_interface(value: &str) -> Result<BindTarget> {
+    const MAX_NAME_LEN: usize = 256;
+    let value = value.trim();
+    if let Ok(ip) = value.parse::<IpAddr>() {
+        if ip.is_unspecified() || ip.is_multicast() {
+            return Err(anyhow!("bind_interface {} is not a local address", ip));
+        }
+        return Ok(BindTarget::Address(ip));
+    }
+    if value.is_empty() || value.len() > MAX_NAME_LEN || value.chars().any(char::is_control) {
+        return Err(anyhow!("bind_interface must be an interface name or an IP address"));
+    }
+    Ok(BindTarget::Interface(value.to_string()))
+}
+
+/// What the session binds to for `bind_interface`. Only Linux binds sockets to a device; other
+/// platforms bind to the address the interface has at startup.
+pub(crate) fn bind_target(value: &str) -> Result<BindTarget> {
+    match parse_bind_interface(value)? {
+        BindTarget::Interface(name) if !cfg!(any(target_os = "linux", target_os = "android")) => {
+            Ok(BindTarget::Address(interface_address(&name)?))
+        }
+        target => Ok(target),
+    }
+}
+
+/// Interface names only; routes and resolvers are not read on this platform yet.
+pub struct InterfaceVpnProbe;
+
+impl VpnProbe for InterfaceVpnProbe {
+    fn interfaces(&self) -> Vec<String> {
+        interfaces_with_address()
+    }
+
+    fn routes(&self) -> Vec<Route> {
+        Vec::new()
+    }
+
+    fn dns_servers(&self) -> Vec<DnsServer> {
+        Vec::new()
+    }
+}
+
+/// Reads `/proc/net/route`, `/proc/net/ipv6_route`, `/etc/resolv.conf` and systemd-resolved's
+/// state under `root`.
+pub struct LinuxVpnProbe {
+    root: PathBuf,
+}
+
+impl LinuxVpnProbe {
+    pub fn new() -> Self {
+        Self::with_root("/")
+    }
+
+    pub fn with_root(root: impl Into<PathBuf>) -> Self {
+        Self { root: root.into() }
+    }
+
+    fn read(&self, path: &str) -> Option<String> {
+        std::fs::read_to_string(self.root.join(path)).ok()
+    }
+
+    /// Per-link resolvers systemd-resolved keeps in `/run/systemd/resolve/netif/<ifindex>`.
+    fn resolved_links(&self) -> HashMap<IpAddr, String> {
+        let names = interface_indexes(&self.root.join("sys/class/net"));
+        let Ok(dir) = std::fs::read_dir(self.root.join("run/systemd/resolve/netif")) else {
+            return HashMap::new();
+        };
+        let mut links = HashMap::new();
+        for entry in dir.flatten() {
+            let Some(name) = entry.file_name().to_str().and_then(|i| names.get(i)).cloned() else {
+                continue;
+            };
+            let Ok(contents) = std::fs::read_to_string(entry.path()) else {
+                continue;
+            };
+            for addr in parse_resolved_link(&contents) {
+                links.insert(addr, name.clone());
+            }
+        }
+        links
+    }
+}
+
+impl Default for LinuxVpnProbe {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl VpnProbe for LinuxVpnProbe {
+    fn interfaces(&self) -> Vec<String> {
+        interfaces_with_address()
+    }
+
+    fn routes(&self) -> Vec<Route> {
+        let mut routes = self.read("proc/net/route").map(|s| parse_route(&s)).unwrap_or_default();
+        routes.extend(self.read("proc/net/ipv6_route").map(|s| parse_ipv6_route(&s)).unwrap_or_default());
+        routes
+    }
+
+    fn dns_servers(&self) -> Vec<DnsServer> {
+        let mut servers = self.read("etc/resolv.conf").map(|s| parse_resolv_conf(&s)).unwrap_or_default();
+        // The systemd-resolved stub only forwards; its upstreams are what the traffic reaches.
+        if !servers.is_empty() && servers.iter().all(|a| a.is_loopback()) {
+            if let Some(upstream) = self.read("run/systemd/resolve/resolv.conf") {
+                servers = parse_resolv_conf(&upstream);
+            }
+        }
+        let links = self.resolved_links();
+        servers
+            .into_iter()
+            .filter(|a| !a.is_loopback())
+            .map(|addr| DnsServer { addr, interface: links.get(&addr).cloned() })
+            .collect()
+    }
+}
+
+/// Parse `/proc/net/route`. Addresses are the raw network-order words printed as host-order
+/// hex, so the native byte order recovers them.
+fn parse_route(contents: &str) -> Vec<Route> {
+    contents
+        .lines()
+        .skip(1)
+        .filter_map(|line| {
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            let [interface, destination, _gateway, flags, _refcnt, _use, metric, mask, ..] = fields[..] else {
+                return None;
+            };
+            let flags = u32::from_str_radix(flags, 16).ok()?;
+            if flags & RTF_UP == 0 || flags & RTF_REJECT != 0 {
+                return None;
+            }
+            let destination = Ipv4Addr::from(u32::from_str_radix(destination, 16).ok()?.to_ne_bytes());
+            let mask = u32::from_str_radix(mask, 16).ok()?;
+            Some(Route {
+                interface: interface.to_string(),
+                destination: IpAddr::V4(destination),
+                prefix_len: mask.count_ones() as u8,
+                metric: metric.parse().ok()?,
+            })
+        })
+        .collect()
+}
+
+/// Parse `/proc/net/ipv6_route`: destination, prefix, source, source prefix, next hop, then
+/// metric, refcount, use and flags in hex, then the interface.
+fn parse_ipv6_route(contents: &str) -> Vec<Route> {
+    contents
+        .lines()
+        .filter_map(|line| {
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            let [destination, prefix_len, _, _, _, metric, _, _, flags, interface] = fields[..] else {
+                return None;
+            };
+            let flags = u32::from_str_radix(flags, 16).ok()?;
+            if flags & RTF_UP == 0 || flags & RTF_REJECT != 0 || interface == "lo" {
+                return None;
+            }
+            Some(Route {
+                interface: interface.to_string(),
+                destination: IpAddr::V6(Ipv6Addr::from(u128::from_str_radix(destination, 16).ok()?)),
+                prefix_len: u8::from_str_radix(prefix_len, 16).ok()?,
+                metric: u32::from_str_radix(metric, 16).ok()?,
+            })
+        })
+        .collect()
+}
+
+fn parse_resolv_conf(contents: &str) -> Vec<IpAddr> {
+    contents
+        .lines()
+        .filter_map(|line| {
+            let mut words = line.split_whitespace();
+            (words.next()? == "nameserver").then_some(())?;
+            // IPv6 resolvers may carry a zone: fe80::1%eth0.
+            words.next()?.split('%').next()?.parse().ok()
+        })
+        .collect()
+}
+
+/// The `DNS=` line of a systemd-resolved link file. Entries may carry a port, an interface
+/// index and a server name: `[fd00::1]:53%3#dns.example`.
+fn parse_resolved_link(contents: &str) -> Vec<IpAddr> {
+    contents
+        .lines()
+        .filter_map(|line| line.strip_prefix("DNS="))
+        .flat_map(|servers| servers.split_whitespace())
+        .filter_map(|entry| {
+            let entry = entry.split(['#', '%']).next()?;
+            entry
+                .parse::<IpAddr>()
+                .ok()
+                .or_else(|| entry.parse::<std::net::SocketAddr>().ok().map(|s| s.ip()))
+        })
+        .collect()
+}
+
+/// `ifindex` to interface name, from `/sys/class/net/<name>/ifindex`.
+fn interface_indexes(sys_class_net: &Path) -> HashMap<String, String> {
+    let Ok(dir) = std::fs::read_dir(sys_class_net) else {
+        return HashMap::new();
+    };
+    dir.flatten()
+        .filter_map(|entry| {
+            let name = entry.file_name().to_str()?.to_string();
+            let index = std::fs::read_to_string(entry.path().join("ifindex")).ok()?;
+            Some((index.trim().to_string(), name))
+        })
+        .collect()
+}
+
+/// The route the kernel picks for `addr`: longest prefix, then lowest metric.
+fn egress(routes: &[Route], addr: IpAddr) -> Option<&Route> {
+    routes
+        .iter()
+        .filter(|r| r.contains(addr))
+        .max_by_key(|r| (r.prefix_len, std::cmp::Reverse(r.metric)))
+}
+
+/// Interfaces that IPv4 and IPv6 internet traffic leave through. `None` when the probe reports
+/// no routes, as on platforms whose routing table is not read.
+pub(crate) fn egress_interfaces(probe: &dyn VpnProbe) -> Option<(Option<String>, Option<String>)> {
+    let routes = probe.routes();
+    if routes.is_empty() {
+        return None;
+    }
+    let via = |addr| egress(&routes, addr).map(|r: &Route| r.interface.clone());
+    Some((via(IpAddr::V4(INTERNET_V4)), via(IpAddr::V6(INTERNET_V6))))
+}
+
+/// Match interface names against common VPN adapter names: provider names (NordVPN, Mullvad,
+/// Proton, ...), protocols (OpenVPN, WireGuard) and TUN/TAP style names (tun0, wg0, ...).
+/// Common non-VPN interfaces (loopback, ethernet, wifi, bridges) are excluded.
+///
+/// **Note**: Regex patterns use `.unwrap()` but are compile-time constants, so panics
+/// would occur at startup if invalid. This is acceptable for static patterns.
+fn looks_like_vpn(name: &str) -> bool {
+    // Patterns are ordered from most specific to least specific
+    let vpn_patterns = [
+        // Specific VPN provider patterns (most reliable)
+        Regex::new(r"(?i)^(nordlynx|nordvpn|mullvad|proton|expressvpn|surfshark|cyberghost|tailscale|wintun)").unwrap(),
+        Regex::new(r"(?i)(private.*internet|pia\b)").unwrap(),
+        // Protocol-specific patterns
+        Regex::new(r"(?i)^(openvpn|wireguard)").unwrap(),
+        // TUN/TAP interface patterns (common VPN interfaces)
+        Regex::new(r"^tun\d+").unwrap(),
+        Regex::new(r"^tap\d+").unwrap(),
+        Regex::new(r"^wg\d+").unwrap(),
+        Regex::new(r"^utun\d+").unwrap(),
+        // Tunnel interfaces (but be careful - some non-VPN tunnels exist)
+        Regex::new(r"(?i)^.*tunnel.*$").unwrap(),
+        // PPP interfaces (often used by VPNs, but can be other things too)
+        Regex::new(r"^ppp\d+").unwrap(),
+    ];
+
+    // Exclude common non-VPN interfaces that might match patterns
+    let exclude_patterns = [
+        Regex::new(r"(?i)^(lo|loopback|eth|wlan|wifi|ethernet|local|bridge|docker|veth)").unwrap(),
+        Regex::new(r"(?i)(bluetooth|pan|wwan)").unwrap(),
+    ];
+
+    let name = name.to_lowercase();
+    if exclude_patterns.iter().any(|pattern| pattern.is_match(&name)) {
+        return false;
+    }
+    if vpn_patterns.iter().any(|pattern| pattern.is_match(&name)) {
+        return true;
+    }
+    if cfg!(target_os = "windows") {
+        if (name.contains("tap") || name.contains("tun") || name.contains("wintun"))
+            && !name.contains("ethernet")
+            && !name.contains("adapter")
+        {
+            return true;
+        }
+        return ["mullvad", "nordvpn", "wireguard", "openvpn", "proton", "expressvpn"]
+            .iter()
+            .any(|p| name.contains(p));
+    }
+    false
+}
+
+pub(crate) fn is_vpn_adapter(source: &VpnSource, name: &str) -> bool {
+    if source.allowed_adapters.is_empty() {
+        source.auto_detect && looks_like_vpn(name)
+    } else {
+        source.allowed_adapters.iter().any(|a| a.eq_ignore_ascii_case(name))
+    }
+}
+
+/// Detect the VPN from what `probe` reports.
+pub(crate) fn status(source: &VpnSource, probe: &dyn VpnProbe, now_ms: u64) -> VpnStatus {
+    let routes = probe.routes();
+    let dns = probe.dns_servers();
+    let egress_v4 = egress(&routes, IpAddr::V4(INTERNET_V4)).map(|r| r.interface.as_str());
+    let egress_v6 = egress(&routes, IpAddr::V6(INTERNET_V6)).map(|r| r.interface.as_str());
+    let default_route_interface = egress_v4.or(egress_v6);
+
+    let candidates: Vec<String> = probe.interfaces().into_iter().filter(|i| is_vpn_adapter(source, i)).collect();
+    // With several adapters up, the one carrying the default route is the one in use.
+    let vpn_interface = candidates
+        .iter()
+        .find(|i| Some(i.as_str()) == default_route_interface)
+        .or(candidates.first())
+        .cloned();
+
+    let (default_route_match, dns_match) = match vpn_interface.as_deref() {
+        Some(vpn) => {
+            let egresses: Vec<&str> = [egress_v4, egress_v6].into_iter().flatten().collect();
+            let default_route_match = !egresses.is_empty() && egresses.iter().all(|i| *i == vpn);
+            let dns_match = !dns.is_empty()
+                && dns.iter().all(|d| {
+                    d.interface.as_deref() == Some(vpn)
+                        || egress(&routes, d.addr).is_some_and(|r| r.interface == vpn)
+                });
+            (default_route_match, dns_match)
+        }
+        None => (false, false),
+    };
+
+    let connected = vpn_interface.is_some();
+    VpnStatus {
+        posture: if connected { VpnPostureState::Connected } else { VpnPostureState::Disconnected },
+        interface_name: vpn_interface.clone(),
+        default_route_interface: default_route_interface.map(str::to_string),
+        dns_servers: dns.iter().map(|d| d.addr.to_string()).collect(),
+        signals: VpnSignals {
+            adapter_match: connected,
+            default_route_match,
+            dns_match,
+            public_ip_match: None,
+        },
+        last_check_ms: now_ms,
+        connection_type: if connected { ConnectionType::Vpn } else { ConnectionType::NonVpn },
+        public_ip: None,
+        detected: Some(connected),
+        interface_name_legacy: vpn_interface,
+    }
+}
+
+pub(crate) fn connected(status: &VpnStatus) -> bool {
+    matches!(status.posture, VpnPostureState::Connected)
+        && matches!(status.connection_type, ConnectionType::Vpn)
+        && status.detected != Some(false)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fixture(name: &str) -> LinuxVpnProbe {
+        LinuxVpnProbe::with_root(Path::new(env!("CARGO_MANIFEST_DIR")).join("fixtures/vpn").join(name))
+    }
+
+    /// Routes and resolvers from a fixture tree, interfaces given by the test.
+    struct FixtureProbe {
+        linux: LinuxVpnProbe,
+        interfaces: Vec<&'static str>,
+    }
+
+    impl VpnProbe for FixtureProbe {
+        fn interfaces(&self) -> Vec<String> {
+            self.interfaces.iter().map(|i| i.to_string()).collect()
+        }
+
+        fn routes(&self) -> Vec<Route> {
+            self.linux.routes()
+        }
+
+        fn dns_servers(&self) -> Vec<DnsServer> {
+            self.linux.dns_servers()
+        }
+    }
+
+    fn auto() -> VpnSource {
+        VpnSource { auto_detect: true, allowed_adapters: vec![] }
+    }
+
+    #[test]
+    fn parses_proc_routes() {
+        let routes = fixture("openvpn-def1").routes();
+        let v4: Vec<_> = routes.iter().filter(|r| r.destination.is_ipv4()).collect();
+        assert_eq!(v4.len(), 6);
+        assert_eq!(
+            *v4[2],
+            Route {
+                interface: "tun0".to_string(),
+                destination: IpAddr::V4(Ipv4Addr::new(128, 0, 0, 0)),
+                prefix_len: 1,
+                metric: 0,
+            }
+        );
+        assert_eq!(v4[4].destination, IpAddr::V4(Ipv4Addr::new(10, 8, 0, 0)));
+        assert_eq!(v4[4].prefix_len, 24);
+        // Loopback and the unreachable default are dropped; the link-local route stays.
+        let v6: Vec<_> = routes.iter().filter(|r| r.destination.is_ipv6()).collect();
+        assert_eq!(v6.len(), 1);
+        assert_eq!(v6[0].interface, "eth0");
+        assert_eq!(v6[0].prefix_len, 64);
+    }
+
+    #[test]
+    fn full_tunnel_matches_every_signal() {
+        let probe = FixtureProbe { linux: fixture("openvpn-def1"), interfaces: vec!["lo", "eth0", "tun0"] };
+        let status = status(&auto(), &probe, 1);
+        assert_eq!(status.interface_name.as_deref(), Some("tun0"));
+        assert_eq!(status.default_route_interface.as_deref(), Some("tun0"));
+        assert_eq!(status.dns_servers, vec!["10.8.0.1".to_string()]);
+        assert!(status.signals.adapter_match);
+        assert!(status.signals.default_route_match);
+        assert!(status.signals.dns_match);
+        assert!(connected(&status));
+    }
+
+    #[test]
+    fn resolved_upstreams_and_ipv6_default_route_are_checked() {
+        let probe = FixtureProbe { linux: fixture("wireguard-resolved"), interfaces: vec!["lo", "eth0", "wg0"] };
+        let dns = probe.dns_servers();
+        assert_eq!(
+            dns,
+            vec![
+                DnsServer { addr: "10.64.0.1".parse().unwrap(), interface: Some("wg0".to_string()) },
+                DnsServer { addr: "192.168.1.1".parse().unwrap(), interface: None },
+            ]
+        );
+
+        let status = status(&auto(), &probe, 1);
+        assert_eq!(status.interface_name.as_deref(), Some("wg0"));
+        assert_eq!(status.default_route_interface.as_deref(), Some("wg0"));
+        // IPv6 still leaves through eth0 and the second resolver is on the LAN.
+        assert!(!status.signals.default_route_match);
+        assert!(!status.signals.dns_match);
+        assert!(connected(&status));
+    }
+
+    #[test]
+    fn allowed_adapters_replace_name_detection() {
+        let probe = FixtureProbe { linux: fixture("wireguard-resolved"), interfaces: vec!["eth0", "wg0", "corp"] };
+        let only = |names: &[&str]| VpnSource {
+            auto_detect: true,
+            allowed_adapters: names.iter().map(|n| n.to_string()).collect(),
+        };
+        assert_eq!(status(&only(&["corp"]), &probe, 1).interface_name.as_deref(), Some("corp"));
+        assert!(!connected(&status(&only(&["tun9"]), &probe, 1)));
+
+        let manual = VpnSource { auto_detect: false, allowed_adapters: vec![] };
+        assert!(!connected(&status(&manual, &probe, 1)));
+    }
+
+    #[test]
+    fn missing_files_leave_signals_false() {
+        let probe = FixtureProbe { linux: fixture("does-not-exist"), interfaces: vec!["wg0"] };
+        let status = status(&auto(), &probe, 1);
+        assert!(status.signals.adapter_match);
+        assert!(!status.signals.default_route_match);
+        assert!(!status.signals.dns_match);
+        assert_eq!(status.default_route_interface, None);
+    }
+
+    #[test]
+    fn parses_bind_interface() {
+        assert_eq!(parse_bind_interface(" wg0 ").unwrap(), BindTarget::Interface("wg0".into()));
+        assert_eq!(
+            parse_bind_interface("10.8.0.2").unwrap(),
+            BindTarget::Address("10.8.0.2".parse().unwrap())
+        );
+        assert!(parse_bind_interface("").is_err());
+        assert!(parse_bind_interface("0.0.0.0").is_err());
+        assert!(parse_bind_interface("ff02::1").is_err());
+    }
+}
@@ -0,0 +1,172 @@
+//! API authentication.
+//!
+//! Every route except [`PUBLIC_PATHS`] needs `Authorization: Bearer <token>`. The admin token
+//! may call anything; the read-only token only `GET`/`HEAD`. Both are generated on first run
+//! and stored in `config.json`. Requests carrying an `Origin` outside the allowlist are
+//! refused before the token is looked at; the same list drives the CORS layer.
+
+use std::sync::Arc;
+
+use anyhow::{Context, Result};
+use axum::{
+    extract::{Request, State},
+    http::{header, HeaderValue, Method, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+    Json,
+};
+use subtle::ConstantTimeEq;
+use tower_http::cors::AllowOrigin;
+use tracing::{info, warn};
+
+use crate::config::{self, DaemonConfig};
+
+/// Reachable without a token: the UI and the Electron shell poll these before they have one.
+const PUBLIC_PATHS: &[&str] = &["/health", "/version"];
+
+const TOKEN_BYTES: usize = 32;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Scope {
+    Read,
+    Admin,
+}
+
+pub struct Auth {
+    admin_token: String,
+    read_token: String,
+    allowed_origins: Vec<String>,
+}
+
+impl Auth {
+    pub fn new(admin_token: String, read_token: String, allowed_origins: Vec<String>) -> Arc<Self> {
+        Arc::new(Self { admin_token, read_token, allowed_origins })
+    }
+
+    pub fn scope_for(&self, provided: &str) -> Option<Scope> {
+        if token_matches(provided, &self.admin_token) {
+            Some(Scope::Admin)
+        } else if token_matches(provided, &self.read_token) {
+            Some(Scope::Read)
+        } else {
+            None
+        }
+    }
+
+    fn origin_allowed(&self, origin: &HeaderValue) -> bool {
+        self.allowed_origins.iter().any(|o| o.as_bytes() == origin.as_bytes())
+    }
+
+    pub fn cors_origins(&self) -> AllowOrigin {
+        AllowOrigin::list(
+            self.allowed_origins
+                .iter()
+                .filter_map(|o| HeaderValue::from_str(o).ok()),
+        )
+    }
+}
+
+fn token_matches(provided: &str, expected: &str) -> bool {
+    let provided = provided.as_bytes();
+    let expected = expected.as_bytes();
+    !expected.is_empty()
+        && provided.len() == expected.len()
+        && provided.ct_eq(expected).unwrap_u8() == 1
+}
+
+/// Random hex token from the OS RNG.
+pub fn generate_token() -> Result<String> {
+    let mut bytes = [0u8; TOKEN_BYTES];
+    getrandom::getrandom(&mut bytes).map_err(|e| anyhow::anyhow!("Failed to generate token: {e}"))?;
+    Ok(bytes.iter().map(|b| format!("{b:02x}")).collect())
+}
+
+/// Fill in missing tokens and save them, so each install gets its own pair on first run.
+pub async fn ensure_tokens(config: &mut DaemonConfig) -> Result<()> {
+    if config.admin_token.is_some() && config.read_token.is_some() {
+        return Ok(());
+    }
+    if config.admin_token.is_none() {
+        config.admin_token = Some(generate_token()?);
+    }
+    if config.read_token.is_none() {
+        config.read_token = Some(generate_token()?);
+    }
+    config::save_config(config).await.context("Failed to save generated API tokens")?;
+    info!("Generated API tokens in {:?}", config::config_path()?);
+    Ok(())
+}
+
+fn bearer_token(req: &Request) -> Option<&str> {
+    req.headers()
+        .get(header::AUTHORIZATION)?
+        .to_str()
+        .ok()?
+        .strip_prefix("Bearer ")
+        .map(str::trim)
+}
+
+fn error(status: StatusCode, msg: &str) -> Response {
+    let mut res = (status, Json(serde_json::json!({"error": msg}))).into_response();
+    if status == StatusCode::UNAUTHORIZED {
+        res.headers_mut()
+            .insert(header::WWW_AUTHENTICATE, HeaderValue::from_static("Bearer"));
+    }
+    res
+}
+
+pub async fn require_auth(State(auth): State<Arc<Auth>>, req: Request, next: Next) -> Response {
+    if let Some(origin) = req.headers().get(header::ORIGIN) {
+        if !auth.origin_allowed(origin) {
+            warn!("Rejected request from disallowed origin {:?}", origin);
+            return error(StatusCode::FORBIDDEN, "origin not allowed");
+        }
+    }
+    if req.method() == Method::OPTIONS || PUBLIC_PATHS.contains(&req.uri().path()) {
+        return next.run(req).await;
+    }
+    let Some(scope) = bearer_token(&req).and_then(|t| auth.scope_for(t)) else {
+        warn!("Unauthorized {} {}", req.method(), req.uri().path());
+        return error(StatusCode::UNAUTHORIZED, "unauthorized");
+    };
+    let read_only = matches!(*req.method(), Method::GET | Method::HEAD);
+    if scope == Scope::Read && !read_only {
+        warn!("Read-only token used for {} {}", req.method(), req.uri().path());
+        return error(StatusCode::FORBIDDEN, "admin token required");
+    }
+    next.run(req).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn auth() -> Arc<Auth> {
+        Auth::new("admin-secret".into(), "read-secret".into(), vec!["http://localhost:5173".into()])
+    }
+
+    #[test]
+    fn tokens_map_to_scopes() {
+        let auth = auth();
+        assert_eq!(auth.scope_for("admin-secret"), Some(Scope::Admin));
+        assert_eq!(auth.scope_for("read-secret"), Some(Scope::Read));
+        assert_eq!(auth.scope_for("admin-secreT"), None);
+        assert_eq!(auth.scope_for("admin"), None);
+        assert_eq!(auth.scope_for(""), None);
+    }
+
+    #[test]
+    fn empty_token_never_matches() {
+        let auth = Auth::new(String::new(), String::new(), vec![]);
+        assert_eq!(auth.scope_for(""), None);
+    }
+
+    #[test]
+    fn generated_tokens_are_unique_hex() {
+        let a = generate_token().unwrap();
+        let b = generate_token().unwrap();
+        assert_eq!(a.len(), TOKEN_BYTES * 2);
+        assert!(a.chars().all(|c| c.is_ascii_hexdigit()));
+        assert_ne!(a, b);
+    }
+}
@@ -1,41 +1,181 @@
 //! Configuration file management for ORC daemon
-//! Stores settings like listen port in platform-specific config directories:
+//! Stores the daemon settings (listen port, bind address, policy, kill switch, rate limits, ...)
+//! and the torrent registry in platform-specific config directories:
 //! - Windows: %APPDATA%\OrcTorrent\config.json
 //! - macOS: ~/Library/Application Support/OrcTorrent/config.json
 //! - Linux: ~/.config/OrcTorrent/config.json
 
 use anyhow::{Context, Result};
+use orc_core::{parse_bind_interface, parse_proxy_url, parse_tracker_urls, DesiredPolicy, KillSwitchSettings, LeakCheckSettings, RateLimits, TrashSettings};
 use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::net::SocketAddr;
 use std::path::PathBuf;
 
+/// Current settings schema version. Bump together with a new entry in [`MIGRATIONS`].
+pub const SCHEMA_VERSION: u32 = 3;
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct DaemonConfig {
+    #[serde(default = "schema_version")]
+    pub version: u32,
     #[serde(default = "default_listen_port")]
     pub listen_port: u16,
+    /// Ask the router to forward `listen_port` via UPnP.
+    #[serde(default = "default_true")]
+    pub upnp_port_forwarding: bool,
+    /// Keep rqbit's own session store (`session/`) so torrents resume without re-adding.
+    #[serde(default = "default_true")]
+    pub session_persistence: bool,
+    /// Store piece bitfields with the session so restarts skip the full recheck.
+    #[serde(default = "default_true")]
+    pub fastresume: bool,
+    /// HTTP API address. `DAEMON_BIND` takes precedence.
+    #[serde(default = "default_bind")]
+    pub bind: String,
+    /// Default save folder. `ORC_DOWNLOAD_DIR` takes precedence; unset means the user's Downloads.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub download_dir: Option<String>,
+    /// Where torrents added without a `save_path` download; unset means `download_dir`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub incomplete_dir: Option<String>,
+    /// Where finished downloads are moved to; unset leaves them where they were downloaded.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub completed_dir: Option<String>,
+    /// Admin API token. `DAEMON_ADMIN_TOKEN` takes precedence.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub admin_token: Option<String>,
+    /// Read-only API token (`GET` routes only). `DAEMON_READ_TOKEN` takes precedence.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub read_token: Option<String>,
+    /// Browser origins allowed to call the API.
+    #[serde(default = "default_allowed_origins")]
+    pub allowed_origins: Vec<String>,
+    #[serde(default)]
+    pub policy: DesiredPolicy,
+    #[serde(default)]
+    pub kill_switch: KillSwitchSettings,
+    #[serde(default)]
+    pub rate_limits: RateLimits,
+    /// Trackers added to every torrent.
+    #[serde(default)]
+    pub trackers: Vec<String>,
+    /// Where `delete_data=trash` puts removed torrents' files, and for how long.
+    #[serde(default)]
+    pub trash: TrashSettings,
+    /// IP-echo URL queried to check that traffic leaves through the VPN.
+    #[serde(default)]
+    pub leak_check: LeakCheckSettings,
+    /// Interface name or local address for all peer, tracker and DHT sockets; unset uses the
+    /// OS routing table.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bind_interface: Option<String>,
+    /// SOCKS5 proxy (`socks5://[user:pass@]host:port`) for torrents in the anonymous and
+    /// tor_assist modes.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub proxy_url: Option<String>,
+}
+
+/// Whether a changed setting takes effect immediately or on the next daemon start.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Apply {
+    Live,
+    Restart,
+}
+
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct SettingSpec {
+    pub key: &'static str,
+    pub apply: Apply,
+}
+
+/// Every user-editable setting. Keys match the `DaemonConfig` field names.
+pub const SETTINGS: &[SettingSpec] = &[
+    SettingSpec { key: "listen_port", apply: Apply::Live },
+    SettingSpec { key: "upnp_port_forwarding", apply: Apply::Restart },
+    SettingSpec { key: "session_persistence", apply: Apply::Restart },
+    SettingSpec { key: "fastresume", apply: Apply::Restart },
+    SettingSpec { key: "bind", apply: Apply::Restart },
+    SettingSpec { key: "download_dir", apply: Apply::Restart },
+    SettingSpec { key: "incomplete_dir", apply: Apply::Live },
+    SettingSpec { key: "completed_dir", apply: Apply::Live },
+    SettingSpec { key: "admin_token", apply: Apply::Restart },
+    SettingSpec { key: "read_token", apply: Apply::Restart },
+    SettingSpec { key: "allowed_origins", apply: Apply::Restart },
+    SettingSpec { key: "policy", apply: Apply::Live },
+    SettingSpec { key: "kill_switch", apply: Apply::Live },
+    SettingSpec { key: "rate_limits", apply: Apply::Live },
+    SettingSpec { key: "trackers", apply: Apply::Restart },
+    SettingSpec { key: "trash", apply: Apply::Live },
+    SettingSpec { key: "leak_check", apply: Apply::Live },
+    SettingSpec { key: "bind_interface", apply: Apply::Restart },
+    SettingSpec { key: "proxy_url", apply: Apply::Restart },
+];
+
+fn schema_version() -> u32 {
+    SCHEMA_VERSION
+}
+
+fn default_bind() -> String {
+    "127.0.0.1:8733".to_string()
+}
+
+/// The Vite dev server. The packaged renderer is a `file://` page, which the desktop app lets
+/// through itself; allowing that origin here would let any local HTML file call the API.
+fn default_allowed_origins() -> Vec<String> {
+    ["http://127.0.0.1:5173", "http://localhost:5173"]
+        .into_iter()
+        .map(String::from)
+        .collect()
 }
 
 fn default_listen_port() -> u16 {
     49000
 }
 
+fn default_true() -> bool {
+    true
+}
+
 const MIN_PORT: u16 = 1024;
-const MAX_PORT: u16 = 65535;
+// rqbit takes an exclusive port range, so 65535 cannot be expressed.
+const MAX_PORT: u16 = 65534;
 
 impl Default for DaemonConfig {
     fn default() -> Self {
         Self {
+            version: SCHEMA_VERSION,
             listen_port: default_listen_port(),
+            upnp_port_forwarding: true,
+            session_persistence: true,
+            fastresume: true,
+            bind: default_bind(),
+            download_dir: None,
+            incomplete_dir: None,
+            completed_dir: None,
+            admin_token: None,
+            read_token: None,
+            allowed_origins: default_allowed_origins(),
+            policy: DesiredPolicy::default(),
+            kill_switch: KillSwitchSettings::default(),
+            rate_limits: RateLimits::default(),
+            trackers: Vec::new(),
+            trash: TrashSettings::default(),
+            leak_check: LeakCheckSettings::default(),
+            bind_interface: None,
+            proxy_url: None,
         }
     }
 }
 
-/// Get the config file path using platform-specific directories.
+/// Get the platform-specific config directory.
 ///
 /// Returns:
-/// - Windows: `%APPDATA%\OrcTorrent\config.json`
-/// - macOS: `~/Library/Application Support/OrcTorrent/config.json`
-/// - Linux: `~/.config/OrcTorrent/config.json`
-pub fn config_path() -> Result<PathBuf> {
+/// - Windows: `%APPDATA%\OrcTorrent`
+/// - macOS: `~/Library/Application Support/OrcTorrent`
+/// - Linux: `~/.config/OrcTorrent`
+pub fn config_dir() -> Result<PathBuf> {
     let config_dir = if cfg!(target_os = "windows") {
         let appdata = std::env::var("APPDATA")
             .context("APPDATA environment variable not set")?;
@@ -50,10 +190,99 @@ pub fn config_path() -> Result<PathBuf> {
         PathBuf::from(home).join(".config").join("OrcTorrent")
     };
     
-    Ok(config_dir.join("config.json"))
+    Ok(config_dir)
+}
+
+/// Get the config file path (`config.json` inside [`config_dir`]).
+pub fn config_path() -> Result<PathBuf> {
+    Ok(config_dir()?.join("config.json"))
+}
+
+/// Get the torrent registry path (`torrents.json` next to `config.json`).
+pub fn registry_path() -> Result<PathBuf> {
+    Ok(config_dir()?.join("torrents.json"))
+}
+
+/// Get the rqbit session store directory (`session/` next to `config.json`).
+pub fn session_persistence_dir() -> Result<PathBuf> {
+    Ok(config_dir()?.join("session"))
 }
 
-/// Load configuration from file, or return default if file doesn't exist
+/// Get the DHT routing table dump path (`dht.json` next to `config.json`).
+pub fn dht_persistence_path() -> Result<PathBuf> {
+    Ok(config_dir()?.join("dht.json"))
+}
+
+type Migration = fn(&mut Map<String, Value>) -> Result<()>;
+
+/// `MIGRATIONS[n]` upgrades a version `n + 1` file to version `n + 2`.
+const MIGRATIONS: &[Migration] = &[migrate_v1_to_v2, migrate_v2_to_v3];
+
+/// v1 files carried no `version` and only the session keys. v2 adds the rest with defaults;
+/// v1 accepted `listen_port` 65535, which rqbit cannot bind, so it is clamped.
+fn migrate_v1_to_v2(map: &mut Map<String, Value>) -> Result<()> {
+    if map.get("listen_port").and_then(Value::as_u64) == Some(65535) {
+        map.insert("listen_port".to_string(), Value::from(MAX_PORT));
+    }
+    Ok(())
+}
+
+/// v2 allowed the `file://` and `null` origins by default, which any local HTML file or
+/// sandboxed iframe has. v3 no longer accepts them.
+fn migrate_v2_to_v3(map: &mut Map<String, Value>) -> Result<()> {
+    if let Some(Value::Array(origins)) = map.get_mut("allowed_origins") {
+        origins.retain(|o| !o.as_str().is_some_and(is_opaque_origin));
+    }
+    Ok(())
+}
+
+// Origins shared by every local file or sandboxed frame, so allowing them allows anyone.
+fn is_opaque_origin(origin: &str) -> bool {
+    origin == "null" || origin.starts_with("file:")
+}
+
+/// Bring a config file of any known version up to [`SCHEMA_VERSION`].
+/// Returns the upgraded document and whether anything had to be migrated.
+fn migrate(value: Value) -> Result<(Value, bool)> {
+    let Value::Object(mut map) = value else {
+        return Err(anyhow::anyhow!("Config file must contain a JSON object"));
+    };
+    let version = match map.get("version") {
+        None => 1,
+        Some(v) => v
+            .as_u64()
+            .and_then(|v| u32::try_from(v).ok())
+            .filter(|v| *v >= 1)
+            .ok_or_else(|| anyhow::anyhow!("Invalid config version: {v}"))?,
+    };
+    if version > SCHEMA_VERSION {
+        return Err(anyhow::anyhow!(
+            "Config version {} is newer than supported version {}",
+            version,
+            SCHEMA_VERSION
+        ));
+    }
+    for (from, migration) in MIGRATIONS.iter().enumerate().skip(version as usize - 1) {
+        migration(&mut map).with_context(|| format!("Failed to migrate config from version {}", from + 1))?;
+    }
+    map.insert("version".to_string(), Value::from(SCHEMA_VERSION));
+    Ok((Value::Object(map), version != SCHEMA_VERSION))
+}
+
+/// Parse, migrate and validate the contents of a config file.
+fn parse_config(content: &str) -> Result<(DaemonConfig, bool)> {
+    let value: Value = serde_json::from_str(content).context("Failed to parse config file")?;
+    let (value, migrated) = migrate(value)?;
+    let config: DaemonConfig = serde_json::from_value(value).context("Failed to parse config file")?;
+
+    // Security: Validate config values
+    validate_config(&config)?;
+
+    Ok((config, migrated))
+}
+
+/// Load configuration from file, or return default if file doesn't exist.
+/// Files written by older versions are migrated and saved back.
 pub async fn load_config() -> Result<DaemonConfig> {
     let config_file = config_path()?;
     
@@ -67,16 +296,17 @@ pub async fn load_config() -> Result<DaemonConfig> {
         .await
         .context("Failed to read config file")?;
     
-    let config: DaemonConfig = serde_json::from_str(&content)
-        .context("Failed to parse config file")?;
-    
-    // Security: Validate config values
-    validate_config(&config)?;
+    let (config, migrated) = parse_config(&content)?;
+    if migrated {
+        tracing::info!("Migrated config file to version {}", SCHEMA_VERSION);
+        save_config(&config).await?;
+    }
     
     Ok(config)
 }
 
-/// Save configuration to file
+/// Save configuration to file (written to a temp file, then renamed into place so readers
+/// such as the settings watcher never see a partial file).
 pub async fn save_config(config: &DaemonConfig) -> Result<()> {
     let config_file = config_path()?;
     if let Some(parent) = config_file.parent() {
@@ -88,27 +318,31 @@ pub async fn save_config(config: &DaemonConfig) -> Result<()> {
     let content = serde_json::to_string_pretty(config)
         .context("Failed to serialize config")?;
     
-    tokio::fs::write(&config_file, content)
+    let tmp_file = config_file.with_extension("json.tmp");
+    tokio::fs::write(&tmp_file, content)
         .await
         .context("Failed to write config file")?;
     #[cfg(unix)]
     {
         use std::os::unix::fs::PermissionsExt;
-        let mut perms = tokio::fs::metadata(&config_file)
+        let mut perms = tokio::fs::metadata(&tmp_file)
             .await
             .context("Failed to get config file metadata")?
             .permissions();
         perms.set_mode(0o600);
-        tokio::fs::set_permissions(&config_file, perms)
+        tokio::fs::set_permissions(&tmp_file, perms)
             .await
             .context("Failed to set config file permissions")?;
     }
+    tokio::fs::rename(&tmp_file, &config_file)
+        .await
+        .context("Failed to replace config file")?;
     
     Ok(())
 }
 
-fn validate_config(config: &DaemonConfig) -> Result<()> {
-    if config.listen_port < MIN_PORT || config.listen_port > MAX_PORT {
+pub fn validate_config(config: &DaemonConfig) -> Result<()> {
+    if !(MIN_PORT..=MAX_PORT).contains(&config.listen_port) {
         return Err(anyhow::anyhow!(
             "Invalid listen_port: {} (must be between {} and {})",
             config.listen_port,
@@ -116,6 +350,112 @@ fn validate_config(config: &DaemonConfig) -> Result<()> {
             MAX_PORT
         ));
     }
+    config
+        .bind
+        .parse::<SocketAddr>()
+        .map_err(|e| anyhow::anyhow!("Invalid bind '{}': {}", config.bind, e))?;
+    if let Some(dir) = &config.download_dir {
+        if dir.trim().is_empty() {
+            return Err(anyhow::anyhow!("download_dir must not be empty"));
+        }
+    }
+    for (key, dir) in [("incomplete_dir", &config.incomplete_dir), ("completed_dir", &config.completed_dir)] {
+        if let Some(dir) = dir {
+            if !std::path::Path::new(dir.trim()).is_absolute() {
+                return Err(anyhow::anyhow!("{} must be an absolute path", key));
+            }
+        }
+    }
+    if let Some(token) = &config.admin_token {
+        validate_token("admin_token", token)?;
+    }
+    if let Some(token) = &config.read_token {
+        validate_token("read_token", token)?;
+        if config.admin_token.as_ref() == Some(token) {
+            return Err(anyhow::anyhow!("read_token must differ from admin_token"));
+        }
+    }
+    const MAX_ORIGINS: usize = 32;
+    if config.allowed_origins.len() > MAX_ORIGINS {
+        return Err(anyhow::anyhow!("Too many allowed_origins (max {})", MAX_ORIGINS));
+    }
+    if let Some(origin) = config
+        .allowed_origins
+        .iter()
+        .find(|o| o.is_empty() || !o.chars().all(|c| c.is_ascii_graphic()) || o.ends_with('/') || is_opaque_origin(o))
+    {
+        return Err(anyhow::anyhow!("Invalid origin in allowed_origins: {:?}", origin));
+    }
+    config.policy.validate()?;
+    config.kill_switch.validate()?;
+    parse_tracker_urls(&config.trackers)?;
+    config.trash.validate()?;
+    config.leak_check.validate()?;
+    if let Some(bind) = &config.bind_interface {
+        parse_bind_interface(bind)?;
+    }
+    if let Some(proxy) = &config.proxy_url {
+        parse_proxy_url(proxy)?;
+    }
     
     Ok(())
 }
+
+fn validate_token(key: &str, token: &str) -> Result<()> {
+    const MAX_TOKEN_LEN: usize = 512;
+    if token.is_empty() || token.len() > MAX_TOKEN_LEN {
+        return Err(anyhow::anyhow!("{} must be 1-{} characters", key, MAX_TOKEN_LEN));
+    }
+    if token.chars().any(|c| c.is_whitespace() || c.is_control()) {
+        return Err(anyhow::anyhow!("{} must not contain whitespace", key));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn legacy_config_is_migrated() {
+        let (config, migrated) = parse_config(r#"{"listen_port": 65535}"#).expect("parse");
+        assert!(migrated);
+        assert_eq!(config.version, SCHEMA_VERSION);
+        assert_eq!(config.listen_port, MAX_PORT);
+        assert_eq!(config.bind, default_bind());
+        assert!(config.session_persistence);
+    }
+
+    #[test]
+    fn opaque_origins_are_dropped_on_migration() {
+        let content = r#"{"version": 2, "allowed_origins": ["http://localhost:5173", "file://", "null"]}"#;
+        let (config, migrated) = parse_config(content).expect("parse");
+        assert!(migrated);
+        assert_eq!(config.allowed_origins, ["http://localhost:5173"]);
+        assert!(parse_config(r#"{"version": 3, "allowed_origins": ["null"]}"#).is_err());
+    }
+
+    #[test]
+    fn current_config_is_not_migrated() {
+        let content = serde_json::to_string(&DaemonConfig::default()).unwrap();
+        let (_, migrated) = parse_config(&content).expect("parse");
+        assert!(!migrated);
+    }
+
+    #[test]
+    fn newer_config_is_rejected() {
+        let content = format!(r#"{{"version": {}}}"#, SCHEMA_VERSION + 1);
+        assert!(parse_config(&content).is_err());
+    }
+
+    #[test]
+    fn invalid_values_are_rejected() {
+        assert!(parse_config(r#"{"version": 2, "bind": "not-an-address"}"#).is_err());
+        assert!(parse_config(r#"{"version": 2, "trackers": ["ftp://tracker.example/announce"]}"#).is_err());
+        assert!(parse_config(r#"{"version": 2, "admin_token": "has space"}"#).is_err());
+        assert!(parse_config(r#"{"version": 2, "admin_token": "same", "read_token": "same"}"#).is_err());
+        assert!(parse_config(r#"{"version": 2, "allowed_origins": ["http://evil.example/"]}"#).is_err());
+        assert!(parse_config(r#"{"version": 2, "completed_dir": "library"}"#).is_err());
+        assert!(parse_config(r#"{"version": 2, "proxy_url": "http://127.0.0.1:8080"}"#).is_err());
+    }
+}
@@ -1,25 +1,37 @@
+mod auth;
 mod config;
+mod settings;
 
-use std::path::{Component, Path, PathBuf};
+use std::path::{Component, PathBuf};
 use std::{net::SocketAddr, sync::Arc, time::Duration};
 
+use librqbit::api::{Api as RqbitApi, TorrentIdOrHash};
+use librqbit::DeleteFiles;
+use serde::Deserialize;
+
 use axum::{
-    extract::{Path, Request, State},
-    http::{HeaderMap, Method, StatusCode},
+    extract::{Path, Query, Request, State},
+    http::{header, HeaderMap, HeaderName, Method, StatusCode},
     middleware::Next,
-    response::IntoResponse,
+    response::{
+        sse::{Event, KeepAlive, Sse},
+        IntoResponse,
+    },
     routing::{get, patch, post},
     Json, Router,
 };
 use tower_http::{
-    cors::{Any, CorsLayer},
+    cors::CorsLayer,
     limit::RequestBodyLimitLayer,
     set_header::SetResponseHeaderLayer,
     trace::TraceLayer,
 };
+use anyhow::Context;
+use futures::StreamExt;
+use tokio::sync::broadcast::error::RecvError;
 use tower::limit::ConcurrencyLimitLayer;
 use tracing::{error, info, warn};
-use subtle::ConstantTimeEq;
+use settings::{Settings, SharedSettings};
 
 use orc_core::{
     get_torrent,
@@ -28,6 +40,10 @@ use orc_core::{
     get_row_snapshot,
     get_policy,
     get_kill_switch,
+    enforce_kill_switch,
+    kill_switch_settings,
+    held_by_kill_switch,
+    test_kill_switch,
     list_torrents,
     net_posture,
     overlay_status,
@@ -42,13 +58,22 @@ use orc_core::{
     rqbit_id_for,
     only_files_for,
     remove_torrent,
+    remove_torrent_unsaved,
+    save_registry,
     set_file_priority,
     set_profile,
     set_running,
+    set_running_unsaved,
     tick,
     trackers_for,
+    add_trackers,
+    remove_trackers,
+    replace_trackers,
+    redact_urls,
+    AddTrackersRequest,
+    RemoveTrackersRequest,
+    PatchTrackersRequest,
     peers_for,
-    mark_announce,
     version,
     wallet_status,
     health,
@@ -60,12 +85,32 @@ use orc_core::{
     PatchTorrentProfileRequest,
     SharedState,
     new_state,
+    listen_status,
+    set_listen_port,
+    PatchListenRequest,
+    SessionConfig,
+    subscribe_events,
+    EventEnvelope,
+    OrcEvent,
+    select_torrents,
+    set_labels,
+    BatchAction,
+    BatchItemResult,
+    BatchRequest,
+    BatchResponse,
+    OrcState,
+    PatchLabelsRequest,
+    DeleteData,
+    DeleteTorrentResponse,
+    MoveTorrentRequest,
+    MoveTorrentResponse,
+    PendingMove,
 };
 
 #[derive(Clone)]
 struct AppCtx {
     state: SharedState,
-    admin_token: String,
+    settings: SharedSettings,
     shutdown: std::sync::Arc<tokio::sync::Notify>,
 }
 
@@ -87,7 +132,7 @@ fn validate_torrent_id(id: &str) -> bool {
 }
 
 /// Normalize a path by resolving `.` and `..` without requiring the path to exist.
-fn normalize_path(path: &Path) -> PathBuf {
+fn normalize_path(path: &std::path::Path) -> PathBuf {
     let mut result = PathBuf::new();
     for comp in path.components() {
         match comp {
@@ -106,7 +151,7 @@ fn normalize_path(path: &Path) -> PathBuf {
 /// Validate save_path: must be under download_dir_path or user home. Returns canonicalized path string.
 fn allowed_save_path(
     save_path: &str,
-    download_dir_path: &Path,
+    download_dir_path: &std::path::Path,
 ) -> Result<String, anyhow::Error> {
     let trimmed = save_path.trim();
     if trimmed.is_empty() {
@@ -148,7 +193,7 @@ async fn validate_content_type(
     request: Request,
     next: Next,
 ) -> impl IntoResponse {
-    if matches!(request.method(), &Method::POST | &Method::PATCH) {
+    if matches!(request.method(), &Method::POST | &Method::PATCH | &Method::DELETE) {
         let has_body = request.headers()
             .get("content-length")
             .and_then(|v| v.to_str().ok())
@@ -178,9 +223,10 @@ async fn validate_content_type(
 }
 
 fn sanitize_error(e: &anyhow::Error, context: &str) -> String {
-    let detailed = format!("{}: {}", context, e);
+    // Tracker URLs can carry a passkey.
+    let detailed = redact_urls(&format!("{}: {}", context, e));
     error!("{}", detailed);
-    let msg = e.to_string();
+    let msg = redact_urls(&e.to_string());
     let mut sanitized = msg.lines().take(1).collect::<String>();
     // Only replace if env var is non-empty; empty pattern would insert between every character
     if let Ok(home) = std::env::var("HOME") {
@@ -253,34 +299,103 @@ async fn main() -> anyhow::Result<()> {
         .with_thread_ids(false)
         .init();
 
-    let admin_token = std::env::var("DAEMON_ADMIN_TOKEN").unwrap_or_else(|_| "".to_string());
-    if !admin_token.is_empty() && admin_token.len() < 32 {
-        warn!("DAEMON_ADMIN_TOKEN is shorter than recommended 32 characters. Consider using a longer token for better security.");
+    // An unreadable file is left alone: starting on defaults would save them over it, tokens
+    // and kill switch included.
+    let mut config = config::load_config().await.with_context(|| {
+        format!(
+            "Failed to load {:?}; fix or remove it to start with defaults",
+            config::config_path().unwrap_or_default()
+        )
+    })?;
+    if let Err(e) = auth::ensure_tokens(&mut config).await {
+        warn!("{e:#}");
     }
-    let bind = std::env::var("DAEMON_BIND").unwrap_or_else(|_| "127.0.0.1:8733".to_string());
-    let addr: SocketAddr = bind.parse().map_err(|e| anyhow::anyhow!("Invalid DAEMON_BIND '{}': {}", bind, e))?;
-    
-    if !addr.ip().is_loopback() && admin_token.is_empty() {
-        return Err(anyhow::anyhow!(
-            "SECURITY ERROR: Binding to non-localhost address {} requires DAEMON_ADMIN_TOKEN to be set. \
-            For production use, always set a strong admin token when exposing to network.",
-            addr.ip()
-        ));
+
+    // Environment variables take precedence over the settings file.
+    let env_or = |var: &str, fallback: Option<String>| {
+        std::env::var(var).ok().filter(|v| !v.is_empty()).or(fallback)
+    };
+    let admin_token = env_or("DAEMON_ADMIN_TOKEN", config.admin_token.clone())
+        .ok_or_else(|| anyhow::anyhow!("No admin token configured"))?;
+    if admin_token.len() < 32 {
+        warn!("Admin token is shorter than recommended 32 characters. Consider using a longer token for better security.");
     }
+    let read_token = env_or("DAEMON_READ_TOKEN", config.read_token.clone()).unwrap_or_default();
+    let auth = auth::Auth::new(admin_token, read_token, config.allowed_origins.clone());
+    let bind = env_or("DAEMON_BIND", Some(config.bind.clone())).unwrap_or_default();
+    let addr: SocketAddr = bind.parse().map_err(|e| anyhow::anyhow!("Invalid bind address '{}': {}", bind, e))?;
     
-    let download_dir = std::env::var("ORC_DOWNLOAD_DIR").unwrap_or_else(|_| default_download_dir());
+    let download_dir = env_or("ORC_DOWNLOAD_DIR", config.download_dir.clone())
+        .unwrap_or_else(default_download_dir);
     tracing::info!("Download directory: {}", download_dir);
-    let config = config::load_config().await
-        .unwrap_or_else(|e| {
-            tracing::warn!("Failed to load config, using defaults: {e}");
-            config::DaemonConfig::default()
-        });
-    
     tracing::info!("Using listen port: {}", config.listen_port);
     tokio::fs::create_dir_all(&download_dir).await?;
 
-    let state = new_state(download_dir, config.listen_port).await?;
+    let registry_path = config::registry_path()
+        .map_err(|e| warn!("Torrent registry disabled, torrents will not survive restarts: {e}"))
+        .ok();
+    let session_persistence_dir = if config.session_persistence {
+        config::session_persistence_dir()
+            .map_err(|e| warn!("rqbit session persistence disabled: {e}"))
+            .ok()
+    } else {
+        None
+    };
+    let dht_persistence_file = config::dht_persistence_path()
+        .map_err(|e| warn!("DHT persistence disabled: {e}"))
+        .ok();
+    let state = new_state(SessionConfig {
+        download_dir,
+        listen_port: config.listen_port,
+        enable_upnp_port_forwarding: config.upnp_port_forwarding,
+        registry_path,
+        session_persistence_dir,
+        fastresume: config.fastresume,
+        disable_dht: false,
+        dht_persistence_file,
+        policy: config.policy.clone(),
+        kill_switch: config.kill_switch.clone(),
+        rate_limits: config.rate_limits.clone(),
+        trackers: config.trackers.clone(),
+        trash: config.trash.clone(),
+        incomplete_dir: config.incomplete_dir.clone(),
+        completed_dir: config.completed_dir.clone(),
+        leak_check: config.leak_check.clone(),
+        bind_interface: config.bind_interface.clone(),
+        proxy_url: config.proxy_url.clone(),
+    })
+    .await?;
+    let settings = Settings::new(config);
+    if let Err(e) = settings::spawn_watcher(state.clone(), settings.clone()) {
+        warn!("Settings file changes will not be picked up until restart: {e:#}");
+    }
     let shutdown_notify = Arc::new(tokio::sync::Notify::new());
+    {
+        let s = state.clone();
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(Duration::from_secs(60 * 60));
+            loop {
+                interval.tick().await;
+                let trash = orc_core::trash(&*s.lock().await);
+                match tokio::task::spawn_blocking(move || trash.purge_expired()).await {
+                    Ok(Ok(0)) => {}
+                    Ok(Ok(n)) => info!("Purged {} expired trash entries", n),
+                    Ok(Err(e)) => warn!("Trash purge failed: {e:#}"),
+                    Err(e) => warn!("Trash purge task failed: {e}"),
+                }
+            }
+        });
+    }
+    {
+        let s = state.clone();
+        tokio::spawn(async move {
+            loop {
+                orc_core::run_leak_check(&s).await;
+                let interval = orc_core::leak_check_interval(&*s.lock().await);
+                tokio::time::sleep(interval).await;
+            }
+        });
+    }
     {
         let s = state.clone();
         tokio::spawn(async move {
@@ -289,13 +404,25 @@ async fn main() -> anyhow::Result<()> {
                 interval.tick().await;
                 let mut guard = s.lock().await;
                 tick(&mut guard);
+                enforce_kill_switch(&mut guard).await;
+                let moves = orc_core::take_pending_moves(&mut guard);
+                if !moves.is_empty() {
+                    let api = rqbit_api(&guard);
+                    for m in moves {
+                        spawn_move(s.clone(), api.clone(), m);
+                    }
+                }
             }
         });
     }
     let cors = CorsLayer::new()
-        .allow_origin(Any)
+        .allow_origin(auth.cors_origins())
         .allow_methods([Method::GET, Method::POST, Method::PATCH, Method::DELETE, Method::OPTIONS])
-        .allow_headers(Any)
+        .allow_headers([
+            header::CONTENT_TYPE,
+            header::AUTHORIZATION,
+            HeaderName::from_static("last-event-id"),
+        ])
         .max_age(Duration::from_secs(3600));
     use axum::http::HeaderValue;
     let security_headers = (
@@ -325,9 +452,19 @@ async fn main() -> anyhow::Result<()> {
         .route("/overlay/status", get(h_overlay_status))
         .route("/net/posture", get(h_net_posture))
         .route("/net/vpn-status", get(h_vpn_status))
+        .route("/net/listen", get(h_listen).patch(h_patch_listen))
         .route("/net/kill-switch", get(h_kill_switch).patch(h_patch_kill_switch))
         .route("/net/kill-switch/test", post(h_kill_switch_test))
         .route("/v1/policy", get(h_policy).patch(h_patch_policy))
+        .route("/v1/settings", get(h_settings).patch(h_patch_settings))
+        .route("/v1/events", get(h_events))
+        .route("/v1/torrents/batch", post(h_batch))
+        .route("/v1/torrents/:id", axum::routing::delete(h_delete_torrent))
+        .route("/v1/torrents/:id/move", post(h_move_torrent))
+        .route(
+            "/v1/torrents/:id/trackers",
+            post(h_add_trackers).delete(h_remove_trackers).patch(h_patch_trackers),
+        )
         .route("/torrents", get(h_list_torrents).post(h_add_torrent))
         .route(
             "/torrents/:id",
@@ -349,6 +486,10 @@ async fn main() -> anyhow::Result<()> {
             "/torrents/:id/profile",
             patch(h_patch_profile),
         )
+        .route(
+            "/torrents/:id/labels",
+            patch(h_patch_labels),
+        )
         .route(
             "/torrents/:id/start",
             post(h_start),
@@ -382,7 +523,8 @@ async fn main() -> anyhow::Result<()> {
             get(h_get_row_snapshot),
         )
         .route("/admin/shutdown", post(h_admin_shutdown))
-        .with_state(AppCtx { state, admin_token, shutdown: shutdown_notify.clone() })
+        .route_layer(axum::middleware::from_fn_with_state(auth, auth::require_auth))
+        .with_state(AppCtx { state, settings, shutdown: shutdown_notify.clone() })
         .layer(axum::middleware::from_fn(validate_content_type))
         .layer(TraceLayer::new_for_http())
         .layer(cors)
@@ -401,6 +543,8 @@ async fn main() -> anyhow::Result<()> {
             tokio::select! {
                 _ = tokio::signal::ctrl_c() => {
                     info!("ctrl-c received; shutting down");
+                    // Ends open event streams, which would otherwise hold graceful shutdown.
+                    shutdown_notify.notify_waiters();
                 }
                 _ = shutdown_notify.notified() => {
                     info!("admin shutdown requested");
@@ -440,8 +584,39 @@ async fn h_net_posture(State(ctx): State<AppCtx>) -> impl IntoResponse {
     Json(net_posture(&guard))
 }
 
-async fn h_vpn_status() -> impl IntoResponse {
-    Json(orc_core::vpn_status())
+async fn h_vpn_status(State(ctx): State<AppCtx>) -> impl IntoResponse {
+    let guard = ctx.state.lock().await;
+    Json(orc_core::vpn_status(&guard))
+}
+
+async fn h_listen(State(ctx): State<AppCtx>) -> impl IntoResponse {
+    let guard = ctx.state.lock().await;
+    Json(listen_status(&guard))
+}
+
+async fn h_patch_listen(
+    State(ctx): State<AppCtx>,
+    Json(req): Json<PatchListenRequest>,
+) -> impl IntoResponse {
+    if let Err(e) = req.validate() {
+        let sanitized = sanitize_error(&e, "Invalid listen request");
+        return (StatusCode::BAD_REQUEST, Json(serde_json::json!({"error": sanitized}))).into_response();
+    }
+
+    let status = {
+        let mut guard = ctx.state.lock().await;
+        match set_listen_port(&mut guard, req.port).await {
+            Ok(s) => s,
+            Err(e) => {
+                let sanitized = sanitize_error(&e, "Failed to change listen port");
+                return (StatusCode::CONFLICT, Json(serde_json::json!({"error": sanitized}))).into_response();
+            }
+        }
+    };
+
+    // Keep the new port across restarts; the live sockets have already moved.
+    settings::record(&ctx.settings, |c| c.listen_port = req.port).await;
+    Json(status).into_response()
 }
 
 async fn h_kill_switch(State(ctx): State<AppCtx>) -> impl IntoResponse {
@@ -454,21 +629,28 @@ async fn h_patch_kill_switch(
     Json(req): Json<PatchKillSwitchRequest>,
 ) -> impl IntoResponse {
     if let Err(e) = req.validate() {
-        let sanitized = sanitize_error(&anyhow::Error::from(e), "Invalid kill switch request");
+        let sanitized = sanitize_error(&e, "Invalid kill switch request");
         return (StatusCode::BAD_REQUEST, Json(serde_json::json!({"error": sanitized}))).into_response();
     }
     
-    let mut guard = ctx.state.lock().await;
-    let out = patch_kill_switch(&mut guard, req);
+    let (out, ks) = {
+        let mut guard = ctx.state.lock().await;
+        let out = patch_kill_switch(&mut guard, req);
+        // Disabling it releases at once rather than on the next tick.
+        enforce_kill_switch(&mut guard).await;
+        (out, kill_switch_settings(&guard))
+    };
+    settings::record(&ctx.settings, |c| c.kill_switch = ks).await;
     Json(out).into_response()
 }
 
 async fn h_kill_switch_test(State(ctx): State<AppCtx>) -> impl IntoResponse {
-    let guard = ctx.state.lock().await;
-    if orc_core::get_kill_switch(&guard).enabled {
-        Json(serde_json::json!({"ok": true, "message": "Kill switch is enabled (simulation)."}))
-    } else {
-        Json(serde_json::json!({"ok": false, "message": "Kill switch is disabled."}))
+    match test_kill_switch(&ctx.state).await {
+        Ok(report) => Json(report).into_response(),
+        Err(e) => {
+            let sanitized = sanitize_error(&e, "Failed to test kill switch");
+            (StatusCode::CONFLICT, Json(serde_json::json!({"error": sanitized}))).into_response()
+        }
     }
 }
 
@@ -482,15 +664,82 @@ async fn h_patch_policy(
     Json(req): Json<PatchPolicyRequest>,
 ) -> impl IntoResponse {
     if let Err(e) = req.desired_patch.validate() {
-        let sanitized = sanitize_error(&anyhow::Error::from(e), "Invalid policy request");
+        let sanitized = sanitize_error(&e, "Invalid policy request");
         return (StatusCode::BAD_REQUEST, Json(serde_json::json!({"error": sanitized}))).into_response();
     }
     
-    let mut guard = ctx.state.lock().await;
-    let out = patch_policy(&mut guard, req.desired_patch);
+    let out = {
+        let mut guard = ctx.state.lock().await;
+        patch_policy(&mut guard, req.desired_patch)
+    };
+    let desired = out.desired.clone();
+    settings::record(&ctx.settings, |c| c.policy = desired).await;
     Json(out).into_response()
 }
 
+async fn h_settings(State(ctx): State<AppCtx>) -> impl IntoResponse {
+    let guard = ctx.settings.lock().await;
+    Json(settings::response(&guard, Vec::new()))
+}
+
+async fn h_patch_settings(
+    State(ctx): State<AppCtx>,
+    Json(patch): Json<serde_json::Value>,
+) -> impl IntoResponse {
+    match settings::update(&ctx.state, &ctx.settings, patch).await {
+        Ok(out) => Json(out).into_response(),
+        Err(settings::UpdateError::Invalid(e)) => {
+            let sanitized = sanitize_error(&e, "Invalid settings");
+            (StatusCode::BAD_REQUEST, Json(serde_json::json!({"error": sanitized}))).into_response()
+        }
+        Err(settings::UpdateError::Failed(e)) => {
+            let sanitized = sanitize_error(&e, "Failed to apply settings");
+            (StatusCode::CONFLICT, Json(serde_json::json!({"error": sanitized}))).into_response()
+        }
+    }
+}
+
+fn sse_event(e: &EventEnvelope) -> Event {
+    Event::default()
+        .id(e.seq.to_string())
+        .event(e.event.name())
+        .json_data(e)
+        .unwrap_or_else(|_| Event::default().event(OrcEvent::Resync.name()))
+}
+
+/// Server-sent state change events. `Last-Event-ID` resumes after that event when it is still
+/// buffered; otherwise the stream starts with a `resync` event.
+async fn h_events(State(ctx): State<AppCtx>, headers: HeaderMap) -> impl IntoResponse {
+    let last_seq = headers
+        .get("last-event-id")
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.trim().parse::<u64>().ok());
+    let (replay, rx) = {
+        let guard = ctx.state.lock().await;
+        subscribe_events(&guard, last_seq)
+    };
+    let live = futures::stream::unfold(rx, |mut rx| async move {
+        match rx.recv().await {
+            Ok(e) => Some((sse_event(&e), rx)),
+            Err(RecvError::Lagged(missed)) => {
+                warn!("Event subscriber lagged, {} events dropped", missed);
+                let resync = Event::default()
+                    .event(OrcEvent::Resync.name())
+                    .json_data(OrcEvent::Resync)
+                    .unwrap_or_default();
+                Some((resync, rx))
+            }
+            Err(RecvError::Closed) => None,
+        }
+    });
+    let shutdown = ctx.shutdown.clone();
+    let stream = futures::stream::iter(replay.iter().map(|e| sse_event(e)).collect::<Vec<_>>())
+        .chain(live)
+        .map(Ok::<_, std::convert::Infallible>)
+        .take_until(async move { shutdown.notified().await });
+    Sse::new(stream).keep_alive(KeepAlive::default())
+}
+
 async fn h_list_torrents(State(ctx): State<AppCtx>) -> impl IntoResponse {
     let guard = ctx.state.lock().await;
     Json(list_torrents(&guard))
@@ -498,10 +747,10 @@ async fn h_list_torrents(State(ctx): State<AppCtx>) -> impl IntoResponse {
 
 async fn h_add_torrent(
     State(ctx): State<AppCtx>,
-    Json(req): Json<AddTorrentRequest>,
+    Json(mut req): Json<AddTorrentRequest>,
 ) -> impl IntoResponse {
     if let Err(e) = req.validate() {
-        let sanitized = sanitize_error(&anyhow::Error::from(e), "Invalid add torrent request");
+        let sanitized = sanitize_error(&e, "Invalid add torrent request");
         return (StatusCode::BAD_REQUEST, Json(serde_json::json!({"error": sanitized}))).into_response();
     }
     
@@ -550,10 +799,23 @@ async fn h_add_torrent(
             }))).into_response();
         }
     }
-    let (api, default_download_path) = {
+    let (api, default_download_path, incomplete_dir) = {
         let guard = ctx.state.lock().await;
-        (rqbit_api(&guard), guard.download_dir_path().clone())
+        (rqbit_api(&guard), guard.download_dir_path().clone(), orc_core::incomplete_dir(&guard))
     };
+    // Per-torrent folder overrides are held to the same roots as save_path.
+    for (key, dir) in [("incomplete_dir", &mut req.incomplete_dir), ("completed_dir", &mut req.completed_dir)] {
+        if let Some(d) = dir.as_mut() {
+            match allowed_save_path(d, &default_download_path) {
+                Ok(allowed) => *d = allowed,
+                Err(e) => {
+                    let sanitized = sanitize_error(&e, &format!("Invalid {key}"));
+                    return (StatusCode::BAD_REQUEST, Json(serde_json::json!({"error": sanitized}))).into_response();
+                }
+            }
+        }
+    }
+    let incomplete_dir = req.incomplete_dir.as_ref().map(PathBuf::from).unwrap_or(incomplete_dir);
     let output_folder = if let Some(s) = req.save_path.as_ref() {
         let t = s.trim();
         if t.is_empty() {
@@ -571,13 +833,15 @@ async fn h_add_torrent(
         None
     }.or_else(|| {
         info_hash_hex.as_ref().map(|h| {
-            default_download_path.join(h.as_str()).to_string_lossy().to_string()
+            incomplete_dir.join(h.as_str()).to_string_lossy().to_string()
         })
     });
-    let mut opts = librqbit::AddTorrentOptions::default();
-    opts.output_folder = output_folder;
     // content is opened, verified (recheck), and only missing/corrupt pieces are downloaded; then seeding works.
-    opts.overwrite = true;
+    let opts = librqbit::AddTorrentOptions {
+        output_folder: output_folder.clone(),
+        overwrite: true,
+        ..Default::default()
+    };
     let rqbit_resp = match &input {
         AddTorrentInput::Url(u) => {
             api.api_add_torrent(librqbit::AddTorrent::from_url(u.as_str()), Some(opts))
@@ -602,9 +866,11 @@ async fn h_add_torrent(
                 || error_lower.contains("file already exists");
             if is_file_exists_error {
                 info!("Files exist on disk but torrent not in state, retrying with overwrite to resume: {error_str}");
-                let mut retry_opts = librqbit::AddTorrentOptions::default();
-                retry_opts.output_folder = output_folder.clone();
-                retry_opts.overwrite = true;
+                let retry_opts = librqbit::AddTorrentOptions {
+                    output_folder: output_folder.clone(),
+                    overwrite: true,
+                    ..Default::default()
+                };
                 match &input {
                     AddTorrentInput::Url(u) => {
                         api.api_add_torrent(librqbit::AddTorrent::from_url(u.as_str()), Some(retry_opts))
@@ -708,7 +974,7 @@ async fn h_patch_file_priority(
     
     // Production Security: Validate request payload
     if let Err(e) = req.validate() {
-        let sanitized = sanitize_error(&anyhow::Error::from(e), "Invalid file priority request");
+        let sanitized = sanitize_error(&e, "Invalid file priority request");
         return (StatusCode::BAD_REQUEST, Json(serde_json::json!({"error": sanitized}))).into_response();
     }
     let (api, rqbit_id, only_files) = {
@@ -744,35 +1010,186 @@ async fn h_patch_profile(
     
     // Production Security: Validate request payload
     if let Err(e) = req.validate() {
-        let sanitized = sanitize_error(&anyhow::Error::from(e), "Invalid profile request");
+        let sanitized = sanitize_error(&e, "Invalid profile request");
         return (StatusCode::BAD_REQUEST, Json(serde_json::json!({"error": sanitized}))).into_response();
     }
     
     let mut guard = ctx.state.lock().await;
+    if rqbit_id_for(&guard, &id).is_none() {
+        return StatusCode::NOT_FOUND.into_response();
+    }
     let profile = orc_core::TorrentProfile { mode: req.mode, hops: req.hops };
-    match set_profile(&mut guard, &id, profile) {
+    match set_profile(&mut guard, &id, profile).await {
         Ok(t) => (StatusCode::OK, Json(t)).into_response(),
-        Err(_) => StatusCode::NOT_FOUND.into_response(),
+        Err(e) => {
+            let sanitized = sanitize_error(&e, "Failed to set profile");
+            (StatusCode::BAD_REQUEST, Json(serde_json::json!({"error": sanitized}))).into_response()
+        }
     }
 }
 
-async fn h_start(State(ctx): State<AppCtx>, Path(id): Path<String>) -> impl IntoResponse {
+async fn h_patch_labels(
+    State(ctx): State<AppCtx>,
+    Path(id): Path<String>,
+    Json(req): Json<PatchLabelsRequest>,
+) -> impl IntoResponse {
     if !validate_torrent_id(&id) {
         return (StatusCode::BAD_REQUEST, Json(serde_json::json!({
             "error": "Invalid torrent ID format"
         }))).into_response();
     }
+    if let Err(e) = req.validate() {
+        let sanitized = sanitize_error(&e, "Invalid labels request");
+        return (StatusCode::BAD_REQUEST, Json(serde_json::json!({"error": sanitized}))).into_response();
+    }
+
+    let mut guard = ctx.state.lock().await;
+    match set_labels(&mut guard, &id, req.labels) {
+        Ok(t) => (StatusCode::OK, Json(t)).into_response(),
+        Err(_) => StatusCode::NOT_FOUND.into_response(),
+    }
+}
+
+/// Kill-switch gate for anything that puts a torrent on the network (start, recheck, announce).
+fn network_blocked(state: &OrcState) -> bool {
+    get_kill_switch(state).enabled && !get_policy(state).effective.network_allowed
+}
+
+fn network_blocked_response() -> axum::response::Response {
+    (StatusCode::FORBIDDEN, Json(serde_json::json!({
+        "error": "Network blocked: VPN kill switch is engaged. Please connect to VPN to resume torrents."
+    }))).into_response()
+}
+
+/// Torrents acted on at once by a batch request.
+const BATCH_CONCURRENCY: usize = 8;
+
+/// The rqbit side of one batch action; orc state is updated by the caller.
+async fn run_batch_action(api: &RqbitApi, action: BatchAction, rqbit_id: usize) -> anyhow::Result<()> {
+    let tid = TorrentIdOrHash::Id(rqbit_id);
+    match action {
+        BatchAction::Start => api.api_torrent_action_start(tid).await?,
+        BatchAction::Stop => api.api_torrent_action_pause(tid).await?,
+        BatchAction::Remove => api.api_torrent_action_forget(tid).await?,
+        BatchAction::Recheck => {
+            // Same as the single-torrent route: a paused torrent has nothing to pause.
+            let _ = api.api_torrent_action_pause(tid).await;
+            api.api_torrent_action_start(tid).await?
+        }
+        BatchAction::Announce => api.api_torrent_action_reannounce(tid, None, false)?,
+    };
+    Ok(())
+}
+
+async fn h_batch(State(ctx): State<AppCtx>, Json(req): Json<BatchRequest>) -> impl IntoResponse {
+    if let Err(e) = req.validate() {
+        let sanitized = sanitize_error(&e, "Invalid batch request");
+        return (StatusCode::BAD_REQUEST, Json(serde_json::json!({"error": sanitized}))).into_response();
+    }
+    if req.ids.iter().flatten().any(|id| !validate_torrent_id(id)) {
+        return (StatusCode::BAD_REQUEST, Json(serde_json::json!({
+            "error": "Invalid torrent ID format"
+        }))).into_response();
+    }
+    let action = req.action;
+
+    // One lock for the gate, the selection and the id lookup, so the whole batch sees one
+    // kill-switch decision.
+    let (api, order, targets, mut results) = {
+        let mut guard = ctx.state.lock().await;
+        if action.needs_network() && network_blocked(&guard) {
+            return network_blocked_response();
+        }
+        let mut ids = match (&req.ids, &req.filter) {
+            (Some(ids), _) => ids.clone(),
+            (None, Some(filter)) => select_torrents(&guard, filter),
+            (None, None) => Vec::new(),
+        };
+        let mut seen = std::collections::HashSet::new();
+        ids.retain(|id| seen.insert(id.clone()));
+        let order: std::collections::HashMap<String, usize> =
+            ids.iter().enumerate().map(|(i, id)| (id.clone(), i)).collect();
+        let mut targets = Vec::with_capacity(ids.len());
+        let mut missing = Vec::new();
+        let mut held = false;
+        for id in ids {
+            match rqbit_id_for(&guard, &id) {
+                // Already paused in rqbit; only keep it from resuming on release.
+                Some(_) if action == BatchAction::Stop && held_by_kill_switch(&guard, &id) => {
+                    held |= set_running_unsaved(&mut guard, &id, false).is_ok();
+                    missing.push(BatchItemResult { id, ok: true, error: None });
+                }
+                Some(rqbit_id) => {
+                    if action == BatchAction::Recheck {
+                        let _ = orc_core::force_checking(&mut guard, &id);
+                    }
+                    targets.push((id, rqbit_id));
+                }
+                None => missing.push(BatchItemResult { id, ok: false, error: Some("Not found".to_string()) }),
+            }
+        }
+        if held {
+            save_registry(&guard);
+        }
+        (rqbit_api(&guard), order, targets, missing)
+    };
+
+    let outcomes: Vec<(String, anyhow::Result<()>)> = futures::stream::iter(targets)
+        .map(|(id, rqbit_id)| {
+            let api = api.clone();
+            async move { (id, run_batch_action(&api, action, rqbit_id).await) }
+        })
+        .buffered(BATCH_CONCURRENCY)
+        .collect()
+        .await;
+
     {
-        let guard = ctx.state.lock().await;
-        let policy = get_policy(&guard);
-        let killswitch = get_kill_switch(&guard);
-        if killswitch.enabled && !policy.effective.network_allowed {
-            return (StatusCode::FORBIDDEN, Json(serde_json::json!({
-                "error": "Network blocked: VPN kill switch is engaged. Please connect to VPN to resume torrents."
-            }))).into_response();
+        let mut guard = ctx.state.lock().await;
+        let mut changed = false;
+        for (id, outcome) in outcomes {
+            match outcome {
+                Ok(()) => {
+                    changed |= match action {
+                        BatchAction::Start => set_running_unsaved(&mut guard, &id, true).is_ok(),
+                        BatchAction::Stop => set_running_unsaved(&mut guard, &id, false).is_ok(),
+                        BatchAction::Remove => remove_torrent_unsaved(&mut guard, &id).is_ok(),
+                        BatchAction::Recheck | BatchAction::Announce => false,
+                    };
+                    results.push(BatchItemResult { id, ok: true, error: None });
+                }
+                Err(e) => {
+                    let error = sanitize_error(&e, "Batch action failed");
+                    results.push(BatchItemResult { id, ok: false, error: Some(error) });
+                }
+            }
+        }
+        // One registry write for the whole batch.
+        if changed {
+            save_registry(&guard);
         }
     }
 
+    results.sort_by_key(|r| order.get(&r.id).copied());
+    let succeeded = results.iter().filter(|r| r.ok).count();
+    (StatusCode::OK, Json(BatchResponse {
+        action,
+        matched: results.len(),
+        succeeded,
+        failed: results.len() - succeeded,
+        results,
+    })).into_response()
+}
+
+async fn h_start(State(ctx): State<AppCtx>, Path(id): Path<String>) -> impl IntoResponse {
+    if !validate_torrent_id(&id) {
+        return (StatusCode::BAD_REQUEST, Json(serde_json::json!({
+            "error": "Invalid torrent ID format"
+        }))).into_response();
+    }
+    if network_blocked(&*ctx.state.lock().await) {
+        return network_blocked_response();
+    }
+
     let (api, rqbit_id) = {
         let guard = ctx.state.lock().await;
         (rqbit_api(&guard), rqbit_id_for(&guard, &id))
@@ -802,15 +1219,23 @@ async fn h_stop(State(ctx): State<AppCtx>, Path(id): Path<String>) -> impl IntoR
         }))).into_response();
     }
     
-    let (api, rqbit_id) = {
+    let (api, rqbit_id, held) = {
         let guard = ctx.state.lock().await;
-        (rqbit_api(&guard), rqbit_id_for(&guard, &id))
+        (rqbit_api(&guard), rqbit_id_for(&guard, &id), held_by_kill_switch(&guard, &id))
     };
 
     let Some(rqbit_id) = rqbit_id else {
         return StatusCode::NOT_FOUND.into_response();
     };
 
+    // A torrent held by the kill switch is already paused; stopping it only keeps it paused
+    // on release.
+    if held {
+        let mut guard = ctx.state.lock().await;
+        let _ = set_running(&mut guard, &id, false);
+        return StatusCode::OK.into_response();
+    }
+
     if let Err(e) = api
         .api_torrent_action_pause(librqbit::api::TorrentIdOrHash::Id(rqbit_id))
         .await
@@ -853,21 +1278,133 @@ async fn h_remove(State(ctx): State<AppCtx>, Path(id): Path<String>) -> impl Int
     StatusCode::OK.into_response()
 }
 
-async fn h_recheck(State(ctx): State<AppCtx>, Path(id): Path<String>) -> impl IntoResponse {
+#[derive(Debug, Deserialize)]
+struct DeleteTorrentQuery {
+    #[serde(default)]
+    delete_data: DeleteData,
+}
+
+async fn h_delete_torrent(
+    State(ctx): State<AppCtx>,
+    Path(id): Path<String>,
+    Query(query): Query<DeleteTorrentQuery>,
+) -> impl IntoResponse {
     if !validate_torrent_id(&id) {
         return (StatusCode::BAD_REQUEST, Json(serde_json::json!({
             "error": "Invalid torrent ID format"
         }))).into_response();
     }
-    {
+
+    let (api, rqbit_id, trash) = {
         let guard = ctx.state.lock().await;
-        let policy = get_policy(&guard);
-        let killswitch = get_kill_switch(&guard);
-        if killswitch.enabled && !policy.effective.network_allowed {
-            return (StatusCode::FORBIDDEN, Json(serde_json::json!({
-                "error": "Network blocked: VPN kill switch is engaged. Please connect to VPN to resume torrents."
-            }))).into_response();
+        (rqbit_api(&guard), rqbit_id_for(&guard, &id), orc_core::trash(&guard))
+    };
+
+    let Some(rqbit_id) = rqbit_id else {
+        return StatusCode::NOT_FOUND.into_response();
+    };
+
+    let files = match query.delete_data {
+        DeleteData::Keep => DeleteFiles::Keep,
+        DeleteData::Delete => DeleteFiles::Delete,
+        DeleteData::Trash => DeleteFiles::MoveTo(trash.entry_for(&id)),
+    };
+    let stats = match api.api_torrent_action_delete_with(TorrentIdOrHash::Id(rqbit_id), files).await {
+        Ok(stats) => stats,
+        Err(e) => {
+            // The engine may have dropped the torrent before failing on its files; don't keep
+            // a record pointing at nothing.
+            if api.mgr_handle(TorrentIdOrHash::Id(rqbit_id)).is_err() {
+                let mut guard = ctx.state.lock().await;
+                let _ = remove_torrent(&mut guard, &id);
+                warn!("Removed torrent {} but failed to handle its files: {:#}", id, e);
+            }
+            let sanitized = sanitize_error(&anyhow::Error::from(e), "Failed to delete torrent");
+            return (StatusCode::BAD_REQUEST, Json(serde_json::json!({"error": sanitized}))).into_response();
+        }
+    };
+
+    let mut guard = ctx.state.lock().await;
+    let _ = remove_torrent(&mut guard, &id);
+    info!("Removed torrent {} (delete_data={:?}, {} files, {} bytes)", id, query.delete_data, stats.files, stats.bytes);
+    let trashed = query.delete_data == DeleteData::Trash;
+    (StatusCode::OK, Json(DeleteTorrentResponse {
+        id,
+        delete_data: query.delete_data,
+        files: stats.files,
+        bytes_freed: if trashed { 0 } else { stats.bytes },
+        bytes_trashed: if trashed { stats.bytes } else { 0 },
+    })).into_response()
+}
+
+async fn h_move_torrent(
+    State(ctx): State<AppCtx>,
+    Path(id): Path<String>,
+    Json(req): Json<MoveTorrentRequest>,
+) -> impl IntoResponse {
+    if !validate_torrent_id(&id) {
+        return (StatusCode::BAD_REQUEST, Json(serde_json::json!({
+            "error": "Invalid torrent ID format"
+        }))).into_response();
+    }
+    if let Err(e) = req.validate() {
+        let sanitized = sanitize_error(&e, "Invalid request");
+        return (StatusCode::BAD_REQUEST, Json(serde_json::json!({"error": sanitized}))).into_response();
+    }
+
+    let (api, pending) = {
+        let mut guard = ctx.state.lock().await;
+        if rqbit_id_for(&guard, &id).is_none() {
+            return StatusCode::NOT_FOUND.into_response();
         }
+        let save_path = match allowed_save_path(&req.save_path, guard.download_dir_path()) {
+            Ok(p) => p,
+            Err(e) => {
+                let sanitized = sanitize_error(&e, "Invalid save_path");
+                return (StatusCode::BAD_REQUEST, Json(serde_json::json!({"error": sanitized}))).into_response();
+            }
+        };
+        let pending = match orc_core::begin_move(&mut guard, &id, &save_path) {
+            Ok(p) => p,
+            Err(e) => {
+                let sanitized = sanitize_error(&e, "Failed to move torrent");
+                return (StatusCode::CONFLICT, Json(serde_json::json!({"error": sanitized}))).into_response();
+            }
+        };
+        (rqbit_api(&guard), pending)
+    };
+
+    let save_path = pending.save_path.clone();
+    spawn_move(ctx.state.clone(), api, pending);
+    (StatusCode::ACCEPTED, Json(MoveTorrentResponse { id, save_path })).into_response()
+}
+
+/// Run a move marked with `begin_move`. Copies across filesystems can take a while; progress
+/// shows up as `moving` in the torrent status.
+fn spawn_move(state: SharedState, api: RqbitApi, pending: PendingMove) {
+    tokio::spawn(async move {
+        let result = api
+            .api_torrent_action_relocate(
+                TorrentIdOrHash::Id(pending.rqbit_id),
+                PathBuf::from(&pending.save_path),
+                pending.progress,
+            )
+            .await
+            .map(|_| ())
+            .map_err(anyhow::Error::from);
+        let mut guard = state.lock().await;
+        orc_core::finish_move(&mut guard, &pending.id, result);
+    });
+}
+
+async fn h_recheck(State(ctx): State<AppCtx>, Path(id): Path<String>) -> impl IntoResponse {
+    if !validate_torrent_id(&id) {
+        return (StatusCode::BAD_REQUEST, Json(serde_json::json!({
+            "error": "Invalid torrent ID format"
+        }))).into_response();
+    }
+    if network_blocked(&*ctx.state.lock().await) {
+        return network_blocked_response();
     }
 
     let (api, rqbit_id) = {
@@ -893,23 +1430,37 @@ async fn h_recheck(State(ctx): State<AppCtx>, Path(id): Path<String>) -> impl In
     StatusCode::OK.into_response()
 }
 
-async fn h_announce(State(ctx): State<AppCtx>, Path(id): Path<String>) -> impl IntoResponse {
+#[derive(Debug, Deserialize)]
+struct AnnounceQuery {
+    /// Only this tracker; all of the torrent's trackers when absent.
+    tracker: Option<String>,
+    /// Ignore the tracker's min interval.
+    #[serde(default)]
+    force: bool,
+}
+
+async fn h_announce(
+    State(ctx): State<AppCtx>,
+    Path(id): Path<String>,
+    Query(query): Query<AnnounceQuery>,
+) -> impl IntoResponse {
     if !validate_torrent_id(&id) {
         return (StatusCode::BAD_REQUEST, Json(serde_json::json!({
             "error": "Invalid torrent ID format"
         }))).into_response();
     }
-    {
-        let guard = ctx.state.lock().await;
-        let policy = get_policy(&guard);
-        let killswitch = get_kill_switch(&guard);
-        if killswitch.enabled && !policy.effective.network_allowed {
-            return (StatusCode::FORBIDDEN, Json(serde_json::json!({
-                "error": "Network blocked: VPN kill switch is engaged. Please connect to VPN to resume torrents."
-            }))).into_response();
-        }
+    if network_blocked(&*ctx.state.lock().await) {
+        return network_blocked_response();
     }
 
+    let tracker = match query.tracker.map(|t| orc_core::parse_tracker_urls(&[t])).transpose() {
+        Ok(t) => t.and_then(|t| t.into_iter().next()),
+        Err(e) => {
+            let sanitized = sanitize_error(&e, "Invalid tracker");
+            return (StatusCode::BAD_REQUEST, Json(serde_json::json!({"error": sanitized}))).into_response();
+        }
+    };
+
     let (api, rqbit_id) = {
         let guard = ctx.state.lock().await;
         (rqbit_api(&guard), rqbit_id_for(&guard, &id))
@@ -918,16 +1469,11 @@ async fn h_announce(State(ctx): State<AppCtx>, Path(id): Path<String>) -> impl I
     let Some(rqbit_id) = rqbit_id else {
         return StatusCode::NOT_FOUND.into_response();
     };
-    {
-        let mut guard = ctx.state.lock().await;
-        let _ = mark_announce(&mut guard, &id);
+    if let Err(e) = api.api_torrent_action_reannounce(TorrentIdOrHash::Id(rqbit_id), tracker, query.force) {
+        let status = e.status();
+        let sanitized = sanitize_error(&anyhow::Error::from(e), "Failed to announce");
+        return (status, Json(serde_json::json!({"error": sanitized}))).into_response();
     }
-    let _ = api
-        .api_torrent_action_pause(librqbit::api::TorrentIdOrHash::Id(rqbit_id))
-        .await;
-    let _ = api
-        .api_torrent_action_start(librqbit::api::TorrentIdOrHash::Id(rqbit_id))
-        .await;
 
     StatusCode::OK.into_response()
 }
@@ -960,6 +1506,86 @@ async fn h_trackers(State(ctx): State<AppCtx>, Path(id): Path<String>) -> impl I
     }
 }
 
+async fn h_add_trackers(
+    State(ctx): State<AppCtx>,
+    Path(id): Path<String>,
+    Json(req): Json<AddTrackersRequest>,
+) -> impl IntoResponse {
+    if !validate_torrent_id(&id) {
+        return (StatusCode::BAD_REQUEST, Json(serde_json::json!({
+            "error": "Invalid torrent ID format"
+        }))).into_response();
+    }
+    if let Err(e) = req.validate() {
+        let sanitized = sanitize_error(&e, "Invalid trackers");
+        return (StatusCode::BAD_REQUEST, Json(serde_json::json!({"error": sanitized}))).into_response();
+    }
+
+    let mut guard = ctx.state.lock().await;
+    if rqbit_id_for(&guard, &id).is_none() {
+        return StatusCode::NOT_FOUND.into_response();
+    }
+    match add_trackers(&mut guard, &id, req).await {
+        Ok(t) => (StatusCode::OK, Json(t)).into_response(),
+        Err(e) => {
+            let sanitized = sanitize_error(&e, "Failed to add trackers");
+            (StatusCode::BAD_REQUEST, Json(serde_json::json!({"error": sanitized}))).into_response()
+        }
+    }
+}
+
+async fn h_remove_trackers(
+    State(ctx): State<AppCtx>,
+    Path(id): Path<String>,
+    Json(req): Json<RemoveTrackersRequest>,
+) -> impl IntoResponse {
+    if !validate_torrent_id(&id) {
+        return (StatusCode::BAD_REQUEST, Json(serde_json::json!({
+            "error": "Invalid torrent ID format"
+        }))).into_response();
+    }
+
+    let mut guard = ctx.state.lock().await;
+    if rqbit_id_for(&guard, &id).is_none() {
+        return StatusCode::NOT_FOUND.into_response();
+    }
+    match remove_trackers(&mut guard, &id, req).await {
+        Ok(t) => (StatusCode::OK, Json(t)).into_response(),
+        Err(e) => {
+            let sanitized = sanitize_error(&e, "Failed to remove trackers");
+            (StatusCode::BAD_REQUEST, Json(serde_json::json!({"error": sanitized}))).into_response()
+        }
+    }
+}
+
+async fn h_patch_trackers(
+    State(ctx): State<AppCtx>,
+    Path(id): Path<String>,
+    Json(req): Json<PatchTrackersRequest>,
+) -> impl IntoResponse {
+    if !validate_torrent_id(&id) {
+        return (StatusCode::BAD_REQUEST, Json(serde_json::json!({
+            "error": "Invalid torrent ID format"
+        }))).into_response();
+    }
+    if let Err(e) = req.validate() {
+        let sanitized = sanitize_error(&e, "Invalid trackers");
+        return (StatusCode::BAD_REQUEST, Json(serde_json::json!({"error": sanitized}))).into_response();
+    }
+
+    let mut guard = ctx.state.lock().await;
+    if rqbit_id_for(&guard, &id).is_none() {
+        return StatusCode::NOT_FOUND.into_response();
+    }
+    match replace_trackers(&mut guard, &id, req).await {
+        Ok(t) => (StatusCode::OK, Json(t)).into_response(),
+        Err(e) => {
+            let sanitized = sanitize_error(&e, "Failed to replace trackers");
+            (StatusCode::BAD_REQUEST, Json(serde_json::json!({"error": sanitized}))).into_response()
+        }
+    }
+}
+
 async fn h_get_row_snapshot(
     State(ctx): State<AppCtx>,
     Path(id): Path<String>,
@@ -977,24 +1603,7 @@ async fn h_get_row_snapshot(
     }
 }
 
-async fn h_admin_shutdown(
-    State(ctx): State<AppCtx>,
-    headers: HeaderMap,
-) -> impl IntoResponse {
-    let provided = headers
-        .get("x-admin-token")
-        .and_then(|v| v.to_str().ok())
-        .unwrap_or("");
-    if !ctx.admin_token.is_empty() {
-        let provided_bytes = provided.as_bytes();
-        let expected_bytes = ctx.admin_token.as_bytes();
-        if provided_bytes.len() != expected_bytes.len() 
-            || provided_bytes.ct_eq(expected_bytes).unwrap_u8() == 0 {
-            warn!("Admin shutdown attempt with invalid token");
-            return (StatusCode::UNAUTHORIZED, Json(serde_json::json!({"error": "unauthorized"}))).into_response();
-        }
-    }
-
+async fn h_admin_shutdown(State(ctx): State<AppCtx>) -> impl IntoResponse {
     info!("admin shutdown accepted");
     ctx.shutdown.notify_waiters();
     (StatusCode::OK, Json(serde_json::json!({"ok": true}))).into_response()
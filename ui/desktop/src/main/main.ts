@@ -1,4 +1,4 @@
-import { app, BrowserWindow, dialog, ipcMain, protocol, shell } from "electron";
+import { app, BrowserWindow, dialog, ipcMain, protocol, session, shell } from "electron";
 import path from "node:path";
 import { fileURLToPath } from "node:url";
 import { spawn, ChildProcess, exec, execSync } from "node:child_process";
@@ -707,6 +707,31 @@ function daemonLogPath(): string {
   }
 }
 
+/** Daemon settings directory; mirrors `config_dir()` in orc-daemon/src/config.rs. */
+function daemonConfigDir(): string {
+  if (process.platform === "win32") {
+    return path.join(process.env.APPDATA || path.join(os.homedir(), "AppData", "Roaming"), "OrcTorrent");
+  }
+  if (process.platform === "darwin") {
+    return path.join(os.homedir(), "Library", "Application Support", "OrcTorrent");
+  }
+  return path.join(os.homedir(), ".config", "OrcTorrent");
+}
+
+/**
+ * Admin token for API calls. The daemon we spawned uses the token we passed in; one started
+ * outside the app uses the per-install token it generated in its config.json.
+ */
+function daemonApiToken(): string | null {
+  if (daemonAdminToken) return daemonAdminToken;
+  try {
+    const config = JSON.parse(readFileSync(path.join(daemonConfigDir(), "config.json"), "utf8"));
+    return typeof config?.admin_token === "string" ? config.admin_token : null;
+  } catch {
+    return null;
+  }
+}
+
 function httpRequestJson(method: "GET" | "POST", pathname: string, headers?: Record<string, string>): Promise<any> {
   return new Promise((resolve, reject) => {
     const req = http.request(
@@ -1846,7 +1871,7 @@ async function gracefulShutdownIfOwned() {
   const logStreamRef = daemonLogStream;
   
   try {
-    await httpRequestJson("POST", "/admin/shutdown", { "x-admin-token": daemonAdminToken });
+    await httpRequestJson("POST", "/admin/shutdown", { authorization: `Bearer ${daemonAdminToken}` });
   } catch {
     // Ignore and fall back to process kill
   }
@@ -2784,8 +2809,47 @@ function continueShutdown(e: Electron.Event) {
   }
 }
 
+/**
+ * The packaged renderer is a file:// page. The daemon does not allow that origin, since any
+ * local HTML file shares it, so requests from this app's own windows go out without an Origin
+ * (they still need the API token) and their responses are opened up for CORS here.
+ */
+function allowRendererDaemonRequests(): void {
+  const filter = { urls: [`http://${DAEMON_HOST}:${DAEMON_PORT}/*`] };
+  const fromFilePage = new Set<number>();
+  session.defaultSession.webRequest.onBeforeSendHeaders(filter, (details, callback) => {
+    const headers = { ...details.requestHeaders };
+    const originKey = Object.keys(headers).find((k) => k.toLowerCase() === "origin");
+    if (originKey && (headers[originKey] === "file://" || headers[originKey] === "null")) {
+      delete headers[originKey];
+      fromFilePage.add(details.id);
+    }
+    callback({ requestHeaders: headers });
+  });
+  session.defaultSession.webRequest.onHeadersReceived(filter, (details, callback) => {
+    if (!fromFilePage.delete(details.id)) {
+      callback({});
+      return;
+    }
+    const headers = Object.fromEntries(
+      Object.entries(details.responseHeaders ?? {}).filter(([k]) => !k.toLowerCase().startsWith("access-control-")),
+    );
+    headers["Access-Control-Allow-Origin"] = ["*"];
+    headers["Access-Control-Allow-Headers"] = ["authorization, content-type, last-event-id"];
+    headers["Access-Control-Allow-Methods"] = ["GET, POST, PUT, PATCH, DELETE, OPTIONS"];
+    const preflight = details.method === "OPTIONS";
+    callback(preflight ? { responseHeaders: headers, statusLine: "HTTP/1.1 204 No Content" } : { responseHeaders: headers });
+  });
+  session.defaultSession.webRequest.onErrorOccurred(filter, (details) => {
+    fromFilePage.delete(details.id);
+  });
+}
+
 app.whenReady().then(async () => {
   try {
+    if (!isDev) {
+      allowRendererDaemonRequests();
+    }
     protocol.handle("app", (request) => {
       const url = request.url;
       if (url === "app://notification-sound" || url.startsWith("app://notification-sound?")) {
@@ -3075,6 +3139,10 @@ app.whenReady().then(async () => {
       return currentDaemonLogPath;
     });
 
+    ipcMain.handle("daemon:api-token", async (): Promise<string | null> => {
+      return daemonApiToken();
+    });
+
     ipcMain.handle("daemon:open-log", async (): Promise<{ success: boolean; error?: string }> => {
       if (!currentDaemonLogPath) {
         return { success: false, error: "Log file path not available" };
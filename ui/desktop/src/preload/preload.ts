@@ -38,6 +38,7 @@ contextBridge.exposeInMainWorld("orc", {
   // Daemon log access and control
   daemon: {
     getLogPath: () => ipcRenderer.invoke("daemon:log-path"),
+    getApiToken: () => ipcRenderer.invoke("daemon:api-token"),
     openLog: () => ipcRenderer.invoke("daemon:open-log"),
     start: () => ipcRenderer.invoke("daemon:start"),
     stop: () => ipcRenderer.invoke("daemon:stop"),
@@ -38,6 +38,7 @@ function trackerStatusLabel(status: string): string {
     case "working": return "Working";
     case "not_working": return "Error";
     case "updating": return "Updating";
+    case "not_contacted": return "Not contacted";
     case "disabled": return "Disabled";
     default: return status;
   }
@@ -70,7 +71,7 @@ export const SwarmTab = memo<SwarmTabProps>(({
   const fetchTrackers = useCallback(async (reportError = false) => {
     if (!online) return;
     try {
-      const data = await getJson<{ trackers: TrackerRow[] }>(`/torrents/${torrent.id}/trackers`);
+      const data = await getJson<{ private?: boolean; trackers: TrackerRow[] }>(`/torrents/${torrent.id}/trackers`);
       setTrackers(data.trackers || []);
     } catch (e) {
       setTrackers([]);
@@ -5,14 +5,20 @@ import { logger } from "../../utils/logger";
 
 interface Tracker {
   url: string;
-  tier?: number;
-  status: "unknown" | "working" | "updating" | "error" | "not_working" | "disabled";
+  /** BEP 12 tier, counted from 0; null for session-wide trackers and the DHT, PeX and LSD rows. */
+  tier?: number | null;
+  /** "not_contacted": a backup in its tier, or in a lower one, while another tracker answers. */
+  status: "unknown" | "working" | "updating" | "not_contacted" | "error" | "not_working" | "disabled";
   seeders: number | null;
   leechers: number | null;
   downloaded?: number | null;
+  peers?: number | null;
   last_announce_ms: number | null;
   next_announce_ms: number | null;
+  interval_secs?: number | null;
+  min_interval_secs?: number | null;
   error: string | null;
+  warning?: string | null;
   announce_count?: number;
   scrape_count?: number;
 }
@@ -25,7 +31,7 @@ interface TrackersTabProps {
   onSuccess: (msg: string) => void;
 }
 
-type SortField = "url" | "status" | "seeders" | "leechers" | "lastAnnounce";
+type SortField = "tier" | "url" | "status" | "seeders" | "leechers" | "lastAnnounce";
 type SortDirection = "asc" | "desc";
 
 export const TrackersTab = memo<TrackersTabProps>(({
@@ -49,8 +55,8 @@ export const TrackersTab = memo<TrackersTabProps>(({
     try {
       const data = await getJson<{ trackers: Tracker[] }>(`/torrents/${torrent.id}/trackers`);
       const trackerList = data.trackers || [];
-      logger.logWithPrefix("TrackersTab", `Received ${trackerList.length} trackers for torrent ${torrent.id}:`, 
-        trackerList.map(t => t.url));
+      // URLs are not logged: private trackers put the passkey in them.
+      logger.logWithPrefix("TrackersTab", `Received ${trackerList.length} trackers for torrent ${torrent.id}`);
       setTrackers(trackerList);
     } catch (err: unknown) {
       const message = err instanceof Error ? err.message : "Failed to fetch trackers";
@@ -132,11 +138,14 @@ export const TrackersTab = memo<TrackersTabProps>(({
     sorted.sort((a, b) => {
       let cmp = 0;
       switch (sortField) {
+        case "tier":
+          cmp = (a.tier ?? Number.MAX_SAFE_INTEGER) - (b.tier ?? Number.MAX_SAFE_INTEGER);
+          break;
         case "url":
           cmp = a.url.localeCompare(b.url);
           break;
         case "status": {
-          const statusOrder: Record<string, number> = { working: 0, updating: 1, unknown: 2, error: 3, not_working: 3, disabled: 4 };
+          const statusOrder: Record<string, number> = { working: 0, updating: 1, not_contacted: 2, unknown: 2, error: 3, not_working: 3, disabled: 4 };
           cmp = (statusOrder[a.status] ?? 2) - (statusOrder[b.status] ?? 2);
           break;
         }
@@ -188,6 +197,7 @@ export const TrackersTab = memo<TrackersTabProps>(({
     switch (status) {
       case "working": return { class: "ok", icon: "✓", label: "Working" };
       case "updating": return { class: "updating", icon: "↻", label: "Updating" };
+      case "not_contacted": return { class: "disabled", icon: "–", label: "Not contacted" };
       case "error":
       case "not_working": return { class: "error", icon: "✗", label: "Error" };
       case "disabled": return { class: "disabled", icon: "○", label: "Disabled" };
@@ -280,6 +290,11 @@ export const TrackersTab = memo<TrackersTabProps>(({
                       Status <SortIndicator field="status" />
                     </div>
                   </th>
+                  <th className="tableHeader sortable" onClick={() => handleSort("tier")} style={{ width: "60px", textAlign: "right" }}>
+                    <div className="tableHeaderContent" style={{ justifyContent: "flex-end" }}>
+                      Tier <SortIndicator field="tier" />
+                    </div>
+                  </th>
                   <th className="tableHeader sortable" onClick={() => handleSort("url")}>
                     <div className="tableHeaderContent">
                       Tracker URL <SortIndicator field="url" />
@@ -318,6 +333,9 @@ export const TrackersTab = memo<TrackersTabProps>(({
                           <span className="trackerStatusLabel">{statusInfo.label}</span>
                         </span>
                       </td>
+                      <td className="tableCell trackerNumber">
+                        {tracker.tier ?? "—"}
+                      </td>
                       <td className="tableCell">
                         <div className="trackerUrlCell">
                           <span className="trackerTypeIcon" title={trackerType.toUpperCase()}>
@@ -330,6 +348,9 @@ export const TrackersTab = memo<TrackersTabProps>(({
                         {tracker.error && (
                           <div className="trackerError">{tracker.error}</div>
                         )}
+                        {!tracker.error && tracker.warning && (
+                          <div className="trackerError">{tracker.warning}</div>
+                        )}
                       </td>
                       <td className="tableCell trackerNumber">
                         {tracker.seeders !== null ? (
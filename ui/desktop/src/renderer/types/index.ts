@@ -12,6 +12,8 @@ export interface Torrent {
   info_hash_hex?: string | null;
   /** Save directory for this torrent (download_dir / sanitized name). */
   save_path?: string | null;
+  /** BEP 27 private flag: no DHT, PeX or LSD, only the torrent's own trackers. */
+  private?: boolean;
 }
 
 export interface TorrentStatus {
@@ -25,7 +27,13 @@ export interface TorrentStatus {
   downloaded_bytes: number;
   peers_seen: number; // Number of peers seen (from JobStatus.peers_seen)
   error?: string; // Error message when state is "error"
-  // TODO: Add seeds tracking when backend supports it
+  swarm?: SwarmHealth; // Largest counts reported by the torrent's trackers (announce or scrape)
+}
+
+export interface SwarmHealth {
+  seeders: number;
+  leechers: number;
+  downloaded: number | null;
 }
 
 // Row snapshot for dual-signal UI component (pieces strip + heartbeat bar)
@@ -113,6 +121,7 @@ export interface NetPosture {
   leak_proof_enabled: boolean;
   state: "unconfigured" | "protected" | "leak_risk";
   last_change_ms: number;
+  reasons: string[];
   vpn_status: VpnStatus;
   kill_switch: KillSwitchConfig;
 }
@@ -16,6 +16,16 @@ function getRequestKey(method: string, path: string, body?: unknown): string {
   return `${method}:${path}:${bodyHash}`;
 }
 
+// Bearer token for the daemon API, fetched once from the main process.
+let apiTokenRequest: Promise<string | null> | null = null;
+
+function getApiToken(): Promise<string | null> {
+  if (!apiTokenRequest) {
+    apiTokenRequest = window.orc?.daemon?.getApiToken?.().catch(() => null) ?? Promise.resolve(null);
+  }
+  return apiTokenRequest;
+}
+
 function createApiError(message: string, status?: number, statusText?: string, isNetworkError = false): ApiError {
   const error = new Error(message) as ApiError;
   error.status = status;
@@ -85,10 +95,20 @@ async function fetchWithRetry(
       lastConnectionAttempt = Date.now();
     }
     
+    const token = await getApiToken();
+    const headers = new Headers(options.headers);
+    if (token) {
+      headers.set("authorization", `Bearer ${token}`);
+    }
     const response = await fetch(url, {
       ...options,
+      headers,
       signal: controller.signal,
     });
+    if (response.status === 401) {
+      // The daemon may have been restarted with a new token; fetch it again next time.
+      apiTokenRequest = null;
+    }
     
     // Clear timeout if request succeeded
     if (timeoutId) {
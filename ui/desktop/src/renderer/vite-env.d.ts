@@ -17,6 +17,8 @@ declare global {
       };
       daemon?: {
         getLogPath: () => Promise<string | null>;
+        /** Bearer token for daemon API calls. */
+        getApiToken: () => Promise<string | null>;
         openLog: () => Promise<{ success: boolean; error?: string }>;
         start: () => Promise<{ success: boolean; error?: string }>;
         stop: () => Promise<{ success: boolean; error?: string }>;